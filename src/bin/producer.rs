@@ -53,10 +53,18 @@ fn main() {
         shares_qty: 100,
         symbol: 100,
         status: 0,
+        order_type: 0,
+        time_in_force: 0,
+        post_only: 0,
+        msg_type: ShmOrder::NEW,
+        session_state: 0,
         side: 0,
         price: 0,
         timestamp: current_time_ns(),
-        _padding: [0; 10],
+        _padding: [0; 5],
+        stop_price: 0,
+        display_qty: 0,
+        _reserved: [0; 4],
     };
 
     let prices = [49999_u64, 50000, 50001];
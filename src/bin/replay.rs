@@ -0,0 +1,88 @@
+use rust_orderbook_2::engine::my_engine::{Engine, MyEngine};
+use rust_orderbook_2::journal::event_journal::read_events;
+use rust_orderbook_2::journal::replay::{first_difference, replay};
+use rust_orderbook_2::orderbook::instrument::InstrumentRegistry;
+use rust_orderbook_2::orderbook::types::SequencedEvent;
+use rust_orderbook_2::publisher::sink::encode_line;
+use std::io::Write;
+
+// runs an input journal through a fresh engine
+//
+//   replay <input journal> [--instruments <file>] [--compare <event journal>]
+//
+// the engine is set up the way the main binary sets it up , from the same instrument file if it had one ,
+// and set up afresh at every restart the input journal recorded
+// without --compare every event is printed , with it the run is checked against the events the original run journaled
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(input_journal) = args.next() else {
+        eprintln!("usage: replay <input journal> [--instruments <file>] [--compare <event journal>]");
+        std::process::exit(2);
+    };
+    let mut instruments = None;
+    let mut compare = None;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--instruments", Some(path)) => instruments = Some(path),
+            ("--compare", Some(dir)) => compare = Some(dir),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let (event_sender, event_rec) = crossbeam::channel::bounded::<SequencedEvent>(100_000);
+    let replay_handle = std::thread::spawn(move || {
+        let registry = instruments.map(|path| InstrumentRegistry::load(&path).expect("Failed to load instruments"));
+        // called again at every restart the journal recorded
+        let setup = || {
+            let mut engine = MyEngine::new(event_sender.clone(), 0);
+            match &registry {
+                Some(registry) => engine.load_instruments(registry),
+                None => engine.add_book(0),
+            }
+            engine
+        };
+        replay(setup, &input_journal)
+    });
+
+    match compare {
+        Some(dir) => {
+            let original = read_events(&dir, 0).expect("Failed to open event journal").map(|event| event.expect("Bad event journal"));
+            match first_difference(original, event_rec.iter()) {
+                None => println!("[REPLAY] output matches {}", dir),
+                Some(divergence) => {
+                    println!("[REPLAY] output differs after {} matching events", divergence.index);
+                    println!("  original: {:?}", divergence.original);
+                    println!("  replayed: {:?}", divergence.replayed);
+                    // let the engine thread finish
+                    event_rec.iter().for_each(drop);
+                    let _ = replay_handle.join();
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            let mut line = Vec::new();
+            for event in event_rec.iter() {
+                line.clear();
+                encode_line(&event, &mut line);
+                if stdout.write_all(&line).is_err() {
+                    break;
+                }
+            }
+            // a closed stdout stops printing , the rest still has to drain for the engine to finish
+            event_rec.iter().for_each(drop);
+        }
+    }
+
+    match replay_handle.join().expect("Replay thread panicked") {
+        Ok(count) => eprintln!("[REPLAY] {} inputs replayed", count),
+        Err(e) => {
+            eprintln!("[REPLAY] stopped: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use crate::orderbook::order::{Order, Side};
+use crate::orderbook::types::{AuctionResult, ExecType, ExecutionReport, Liquidity, MatchResult, OrderAmended, OrderBookError, OrderId, PostOnlyOutcome};
+
+// what the engine remembers about a live order to fill in its reports
+#[derive(Debug, Clone, Copy)]
+struct OrderState{
+    client_id : u32 ,
+    symbol : u32 ,
+    side : Side ,
+    cum_qty : u32 ,
+    leaves_qty : u32 ,
+    // sum of price * qty over every fill
+    notional : u128
+}
+
+impl OrderState{
+    fn report(&self , order_id : OrderId , exec_type : ExecType)->ExecutionReport{
+        ExecutionReport{
+            order_id ,
+            client_id : self.client_id ,
+            symbol : self.symbol ,
+            side : Some(self.side) ,
+            exec_type ,
+            order_qty : self.cum_qty + self.leaves_qty ,
+            cum_qty : self.cum_qty ,
+            leaves_qty : self.leaves_qty ,
+            avg_price : if self.cum_qty == 0 { 0.0 } else { self.notional as f64 / self.cum_qty as f64 } ,
+            last_price : 0 ,
+            last_qty : 0 ,
+            liquidity : None ,
+            reject_reason : None
+        }
+    }
+
+    fn fill(&mut self , order_id : OrderId , price : u64 , qty : u32 , liquidity : Liquidity)->ExecutionReport{
+        self.cum_qty += qty;
+        self.leaves_qty = self.leaves_qty.saturating_sub(qty);
+        self.notional += price as u128 * qty as u128;
+        let exec_type = if self.leaves_qty == 0 { ExecType::Fill } else { ExecType::PartialFill };
+        ExecutionReport{ last_price : price , last_qty : qty , liquidity : Some(liquidity) , ..self.report(order_id, exec_type) }
+    }
+
+    // the order stays live with `qty` less than it had
+    fn reduce(&mut self , order_id : OrderId , qty : u32)->ExecutionReport{
+        self.leaves_qty = self.leaves_qty.saturating_sub(qty);
+        self.report(order_id, ExecType::Restated)
+    }
+
+    // the rest of the order leaves the book without trading
+    fn close(&mut self , order_id : OrderId , exec_type : ExecType)->ExecutionReport{
+        self.leaves_qty = 0;
+        self.report(order_id, exec_type)
+    }
+}
+
+// turns what the books return into execution reports for every order involved
+// an order is tracked from its New report until it is filled , cancelled or expired
+#[derive(Debug, Default)]
+pub struct ExecutionTracker{
+    orders : HashMap<OrderId , OrderState> ,
+    // orders that stopped being tracked since the last take_closed , filled , cancelled , expired or killed
+    closed : Vec<OrderId>
+}
+
+impl ExecutionTracker{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    pub fn len(&self)->usize{
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.orders.is_empty()
+    }
+
+    // `order` as it was before it reached the book
+    pub fn accepted(&mut self , order : &Order)->ExecutionReport{
+        let state = OrderState{
+            client_id : order.client_id ,
+            symbol : order.symbol ,
+            side : order.side ,
+            cum_qty : 0 ,
+            leaves_qty : order.total_qty() ,
+            notional : 0
+        };
+        self.orders.insert(order.order_id, state);
+        state.report(order.order_id, ExecType::New)
+    }
+
+    // reports for the taker , every maker it traded with or knocked out through self trade prevention ,
+    // and whatever of the taker did not rest
+    pub fn matched(&mut self , result : &MatchResult)->Vec<ExecutionReport>{
+        let mut reports = Vec::new();
+        for fill in &result.fills.fills{
+            if let Some(taker) = self.orders.get_mut(&fill.taker_order_id){
+                reports.push(taker.fill(fill.taker_order_id, fill.price, fill.quantity, Liquidity::Taker));
+            }
+            if let Some(maker) = self.orders.get_mut(&fill.maker_order_id){
+                reports.push(maker.fill(fill.maker_order_id, fill.price, fill.quantity, Liquidity::Maker));
+            }
+        }
+        for cancelled in &result.self_trade.cancelled_makers{
+            if let Some(report) = self.cancelled(cancelled.order_id){
+                reports.push(report);
+            }
+        }
+        for reduced in &result.self_trade.reduced_makers{
+            if let Some(maker) = self.orders.get_mut(&reduced.order_id){
+                reports.push(maker.reduce(reduced.order_id, reduced.cancelled_qty));
+            }
+        }
+        // self trade prevention took quantity off a taker whose remainder still rests
+        if let Some(taker) = self.orders.get_mut(&result.order_id)
+            && result.remaining_qty > 0 && taker.leaves_qty > result.remaining_qty{
+                reports.push(taker.reduce(result.order_id, taker.leaves_qty - result.remaining_qty));
+        }
+        if let Some(taker) = self.orders.get_mut(&result.order_id)
+            && result.cancelled_qty > 0 && result.remaining_qty == 0 && taker.leaves_qty > 0{
+                let exec_type = if result.post_only == Some(PostOnlyOutcome::Rejected){
+                    ExecType::Rejected
+                }
+                else if result.self_trade.taker_cancelled_qty > 0{
+                    ExecType::Cancelled
+                }
+                else {
+                    ExecType::Expired
+                };
+                reports.push(taker.close(result.order_id, exec_type));
+        }
+        let touched = result.fills.fills.iter().flat_map(|fill| [fill.taker_order_id , fill.maker_order_id]);
+        self.forget_done(touched.chain([result.order_id]));
+        reports
+    }
+
+    // both sides of an uncross fill as resting orders
+    pub fn uncrossed(&mut self , result : &AuctionResult)->Vec<ExecutionReport>{
+        let mut reports = Vec::new();
+        for fill in &result.fills.fills{
+            for order_id in [fill.taker_order_id , fill.maker_order_id]{
+                if let Some(state) = self.orders.get_mut(&order_id){
+                    reports.push(state.fill(order_id, fill.price, fill.quantity, Liquidity::Auction));
+                }
+            }
+        }
+        self.forget_done(result.fills.fills.iter().flat_map(|fill| [fill.taker_order_id , fill.maker_order_id]));
+        reports
+    }
+
+    // only the orders a result touched can have finished , no need to look at the rest
+    fn forget_done(&mut self , order_ids : impl Iterator<Item = OrderId>){
+        for order_id in order_ids{
+            if self.orders.get(&order_id).is_some_and(|state| state.leaves_qty == 0){
+                self.orders.remove(&order_id);
+                self.closed.push(order_id);
+            }
+        }
+    }
+
+    pub fn cancelled(&mut self , order_id : OrderId)->Option<ExecutionReport>{
+        let mut state = self.orders.remove(&order_id)?;
+        self.closed.push(order_id);
+        Some(state.close(order_id, ExecType::Cancelled))
+    }
+
+    // a tracked order the book dropped without trading , e.g. a stop that fired but could not match
+    pub fn killed(&mut self , order_id : OrderId , reason : OrderBookError)->Option<ExecutionReport>{
+        let mut state = self.orders.remove(&order_id)?;
+        self.closed.push(order_id);
+        Some(ExecutionReport{ reject_reason : Some(reason) , ..state.close(order_id, ExecType::Rejected) })
+    }
+
+    // the orders that are done since the last call , whatever else keys on their ids can let go of them
+    pub fn take_closed(&mut self)->std::vec::Drain<'_ , OrderId>{
+        self.closed.drain(..)
+    }
+
+    // `new_qty` is what is left to trade , an amend to zero is a cancel
+    pub fn replaced(&mut self , amended : &OrderAmended)->Option<ExecutionReport>{
+        if amended.new_qty == 0{
+            return self.cancelled(amended.order_id);
+        }
+        let state = self.orders.get_mut(&amended.order_id)?;
+        state.leaves_qty = amended.new_qty;
+        Some(state.report(amended.order_id, ExecType::Replaced))
+    }
+
+    // a refused new order , cancel or amend reported under the id the client sent . a cancel or amend
+    // of a `live_order` the engine still tracks carries that order's quantities , it keeps working
+    pub fn rejected(&self , order_id : OrderId , live_order : Option<OrderId> , client_id : u32 , symbol : u32 , side : Option<Side> , reason : OrderBookError)->ExecutionReport{
+        let report = match live_order.and_then(|live_order| self.orders.get(&live_order)){
+            Some(state) => state.report(order_id, ExecType::Rejected),
+            // nothing tracked , the report carries what the message said 
+            None => ExecutionReport{
+                side ,
+                ..OrderState{ client_id , symbol , side : Side::Bid , cum_qty : 0 , leaves_qty : 0 , notional : 0 }.report(order_id, ExecType::Rejected)
+            }
+        };
+        ExecutionReport{ reject_reason : Some(reason) , ..report }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::types::Fill;
+
+    #[test]
+    fn test_taker_and_maker_reports() {
+        let mut tracker = ExecutionTracker::new();
+        let maker = Order{ client_id : 1 , ..Order::new(1, Side::Ask, 30, 100, 0, 0) };
+        let taker = Order{ client_id : 2 , ..Order::new(2, Side::Bid, 50, 101, 1, 0) };
+        assert_eq!(tracker.accepted(&maker).exec_type, ExecType::New);
+        tracker.accepted(&taker);
+
+        let mut result = MatchResult::new(2, 50);
+        result.add_transaction(Fill::new(100, 30, 2, 1));
+        let reports = tracker.matched(&result);
+        assert_eq!(reports.len(), 2);
+        assert_eq!((reports[0].exec_type , reports[0].cum_qty , reports[0].leaves_qty) , (ExecType::PartialFill , 30 , 20));
+        assert_eq!(reports[0].liquidity, Some(Liquidity::Taker));
+        // the maker sees the trade from its own side
+        assert_eq!((reports[1].exec_type , reports[1].side , reports[1].client_id) , (ExecType::Fill , Some(Side::Ask) , 1));
+        assert_eq!(reports[1].avg_price, 100.0);
+        // the filled maker is no longer tracked
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_leftover_expires_and_replace_keeps_cum() {
+        let mut tracker = ExecutionTracker::new();
+        tracker.accepted(&Order::new(1, Side::Bid, 10, 100, 0, 0));
+        let mut result = MatchResult::new(1, 10);
+        result.add_transaction(Fill::new(100, 4, 1, 9));
+        result.cancelled_qty = 6;
+        result.remaining_qty = 0;
+        let reports = tracker.matched(&result);
+        assert_eq!(reports.last().unwrap().exec_type, ExecType::Expired);
+        assert_eq!(reports.last().unwrap().cum_qty, 4);
+        assert!(tracker.is_empty());
+
+        tracker.accepted(&Order::new(2, Side::Bid, 10, 100, 0, 0));
+        let mut result = MatchResult::new(2, 10);
+        result.add_transaction(Fill::new(100, 4, 2, 9));
+        tracker.matched(&result);
+        let amended = OrderAmended{ order_id : 2 , side : Side::Bid , old_price : 100 , old_qty : 6 , new_price : 100 , new_qty : 3 , priority_kept : true };
+        let replaced = tracker.replaced(&amended).unwrap();
+        assert_eq!((replaced.order_qty , replaced.cum_qty , replaced.leaves_qty) , (7 , 4 , 3));
+        assert_eq!(tracker.cancelled(2).unwrap().leaves_qty, 0);
+        assert!(tracker.cancelled(2).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use crate::orderbook::types::{Bar, Trade};
+
+pub const ONE_SECOND : u64 = 1_000_000_000;
+pub const ONE_MINUTE : u64 = 60 * ONE_SECOND;
+pub const FIVE_MINUTES : u64 = 5 * ONE_MINUTE;
+
+fn open_bar(trade : &Trade , interval : u64 , start : u64)->Bar{
+    Bar {
+        symbol : trade.symbol ,
+        interval ,
+        start ,
+        open : trade.price ,
+        high : trade.price ,
+        low : trade.price ,
+        close : trade.price ,
+        volume : trade.quantity as u64 ,
+        turnover : trade.price as u128 * trade.quantity as u128 ,
+        trade_count : 1
+    }
+}
+
+fn add_to_bar(bar : &mut Bar , trade : &Trade){
+    bar.high = bar.high.max(trade.price);
+    bar.low = bar.low.min(trade.price);
+    bar.close = trade.price;
+    bar.volume += trade.quantity as u64;
+    bar.turnover += trade.price as u128 * trade.quantity as u128;
+    bar.trade_count += 1;
+}
+
+// everything traded in one symbol since the session started , plus the bars still open 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolStats{
+    pub symbol : u32 ,
+    pub open : u64 ,
+    pub high : u64 ,
+    pub low : u64 ,
+    pub close : u64 ,
+    pub volume : u64 ,
+    pub turnover : u128 ,
+    pub trade_count : u64 ,
+    // one slot per configured interval , None until something trades in it 
+    pub bars : Vec<Option<Bar>>
+}
+
+impl SymbolStats{
+    fn new(trade : &Trade , intervals : usize)->Self{
+        Self {
+            symbol : trade.symbol ,
+            open : trade.price ,
+            high : trade.price ,
+            low : trade.price ,
+            close : trade.price ,
+            volume : 0 ,
+            turnover : 0 ,
+            trade_count : 0 ,
+            bars : vec![None ; intervals]
+        }
+    }
+
+    pub fn vwap(&self)->Option<f64>{
+        (self.volume > 0).then(|| self.turnover as f64 / self.volume as f64)
+    }
+}
+
+// session statistics and time bars per symbol , fed from the trade prints 
+// bars only exist once something traded in them and close as soon as time moves past their end , 
+// either through a later trade or a message with a later timestamp 
+#[derive(Debug, Clone)]
+pub struct MarketStats{
+    // bar lengths in nanoseconds 
+    pub intervals : Vec<u64> ,
+    pub symbols : BTreeMap<u32 , SymbolStats>
+}
+
+impl Default for MarketStats{
+    fn default()->Self{
+        Self::new(vec![ONE_SECOND , ONE_MINUTE , FIVE_MINUTES])
+    }
+}
+
+impl MarketStats{
+    pub fn new(intervals : Vec<u64>)->Self{
+        assert!(intervals.iter().all(|&interval| interval > 0), "bar intervals must be positive");
+        Self { intervals , symbols : BTreeMap::new() }
+    }
+
+    pub fn get(&self , symbol : u32)->Option<&SymbolStats>{
+        self.symbols.get(&symbol)
+    }
+
+    // returns the bars this trade closed , the trade itself goes into the next ones 
+    pub fn on_trade(&mut self , trade : &Trade)->Vec<Bar>{
+        let intervals = self.intervals.len();
+        let stats = self.symbols.entry(trade.symbol).or_insert_with(|| SymbolStats::new(trade, intervals));
+        stats.high = stats.high.max(trade.price);
+        stats.low = stats.low.min(trade.price);
+        stats.close = trade.price;
+        stats.volume += trade.quantity as u64;
+        stats.turnover += trade.price as u128 * trade.quantity as u128;
+        stats.trade_count += 1;
+
+        let mut closed = Vec::new();
+        for (slot , &interval) in stats.bars.iter_mut().zip(&self.intervals){
+            let start = trade.timestamp - trade.timestamp % interval;
+            match slot{
+                // a trade stamped before the open bar started is late , it still counts in the open bar 
+                Some(bar) if trade.timestamp < bar.end() => add_to_bar(bar, trade),
+                _ => {
+                    if let Some(bar) = slot.replace(open_bar(trade, interval, start)){
+                        closed.push(bar);
+                    }
+                }
+            }
+        }
+        closed
+    }
+
+    // closes every open bar that ended at or before `now` , oldest symbol first so the order is always the same 
+    pub fn on_time(&mut self , now : u64)->Vec<Bar>{
+        let mut closed = Vec::new();
+        for stats in self.symbols.values_mut(){
+            for slot in stats.bars.iter_mut(){
+                if slot.is_some_and(|bar| bar.end() <= now){
+                    closed.extend(slot.take());
+                }
+            }
+        }
+        closed
+    }
+
+    // a new session starts from nothing , bars still open are handed back closed 
+    pub fn reset(&mut self , symbol : u32)->Vec<Bar>{
+        self.symbols.remove(&symbol)
+            .map(|stats| stats.bars.into_iter().flatten().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::order::Side;
+
+    fn trade(price : u64 , quantity : u32 , timestamp : u64)->Trade{
+        Trade { trade_id : 0 , symbol : 1 , aggressor_side : Some(Side::Bid) , price , quantity , timestamp }
+    }
+
+    #[test]
+    fn test_session_stats_and_vwap() {
+        let mut stats = MarketStats::default();
+        stats.on_trade(&trade(100, 10, 0));
+        stats.on_trade(&trade(104, 30, 1));
+        stats.on_trade(&trade(98, 10, 2));
+        let symbol = stats.get(1).unwrap();
+        assert_eq!((symbol.open , symbol.high , symbol.low , symbol.close) , (100 , 104 , 98 , 98));
+        assert_eq!(symbol.volume, 50);
+        assert_eq!(symbol.vwap(), Some(102.0));
+        assert!(stats.get(2).is_none());
+    }
+
+    #[test]
+    fn test_bars_close_on_later_trade_or_time() {
+        let mut stats = MarketStats::new(vec![ONE_SECOND , ONE_MINUTE]);
+        assert!(stats.on_trade(&trade(100, 1, 100)).is_empty());
+        stats.on_trade(&trade(102, 1, ONE_SECOND - 1));
+        // the next second closes the 1s bar , the minute bar keeps going 
+        let closed = stats.on_trade(&trade(101, 2, ONE_SECOND + 5));
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].start , closed[0].open , closed[0].high , closed[0].close , closed[0].volume) , (0 , 100 , 102 , 102 , 2));
+
+        assert!(stats.on_time(ONE_SECOND + 10).is_empty());
+        let closed = stats.on_time(ONE_MINUTE);
+        assert_eq!(closed.iter().map(|bar| bar.interval).collect::<Vec<_>>(), vec![ONE_SECOND , ONE_MINUTE]);
+        assert_eq!(closed[1].volume, 4);
+        assert_eq!(closed[1].vwap(), 101.0);
+        assert!(stats.get(1).unwrap().bars.iter().all(Option::is_none));
+    }
+}
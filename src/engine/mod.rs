@@ -1 +1,4 @@
-pub mod my_engine;
\ No newline at end of file
+pub mod my_engine;
+pub mod order_ids;
+pub mod execution;
+pub mod market_stats;
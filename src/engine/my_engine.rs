@@ -1,8 +1,15 @@
 use std::collections::HashMap;
-use crate::orderbook::order::{Order, Side};
-use crate::orderbook::types::Event ;
+use crate::orderbook::order::{Order, PostOnly, ShmOrder, Side, TimeInForce, Type};
+use crate::orderbook::types::{AuctionResult, Event, Fills, SequencedEvent, Trade, MatchResult, OrderBookError, OrderCancelled, OrderId, OrderIdAssigned, OrderRejected, SessionChanged};
+use crate::engine::order_ids::OrderIds;
+use crate::engine::execution::ExecutionTracker;
+use crate::engine::market_stats::MarketStats;
+use crate::orderbook::session::SessionState;
+use crate::orderbook::instrument::{Instrument, InstrumentRegistry};
 use crate::orderbook::order_book::OrderBook;
 use crate::shm::queue::Queue;
+use crate::journal::command_journal::{CommandJournal, JournaledInput};
+use crate::journal::segment::JournalError;
 
 pub trait Engine{
     fn add_book(&mut self , symbol : u32);
@@ -13,16 +20,48 @@ pub trait Engine{
     fn has_book(&self , symbol : u32)->bool;
 }
 
+// where the message being processed came from 
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputContext{
+    pub position : u64 ,
+    pub symbol : u32 ,
+    // timestamp the message was sent with 
+    pub timestamp : u64 ,
+    pub received_at : u64
+}
+
+fn now_ns()->u64{
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0 , |elapsed| elapsed.as_nanos() as u64)
+}
+
 pub struct MyEngine{
     // the engine will own all the orderbooks
     pub engine_id :usize ,
     pub book_count : usize, 
     pub books : HashMap< u32 , OrderBook>,
-    pub event_publisher : crossbeam::channel::Sender<Event>
+    pub event_publisher : crossbeam::channel::Sender<SequencedEvent>,
+    // last engine wide sequence number handed out 
+    pub seq : u64,
+    pub symbol_seqs : HashMap<u32 , u64>,
+    // the message being processed , every event it causes is stamped with it 
+    pub input : InputContext,
+    pub next_input_position : u64,
+    // last trade id handed out , shared by every book of the engine 
+    pub trade_id : u64,
+    // client order ids to the ids the books use , shared by every book of the engine 
+    pub order_ids : OrderIds,
+    // cumulative state of every live order , feeds the execution reports 
+    pub executions : ExecutionTracker,
+    // session figures and time bars per symbol built from the trade prints 
+    pub stats : MarketStats,
+    // every input is handed to it before it is processed , so the run can be replayed 
+    pub input_journal : Option<CommandJournal>,
+    // events carry the journaled receive time as their emit time instead of reading the clock 
+    pub replaying : bool
 }
 
 impl MyEngine{
-    pub fn new(event_publisher :  crossbeam::channel::Sender<Event>, engine_id : usize)->Self {
+    pub fn new(event_publisher :  crossbeam::channel::Sender<SequencedEvent>, engine_id : usize)->Self {
         // initialise the publisher channel here 
         
             Self{
@@ -30,9 +69,369 @@ impl MyEngine{
                 book_count : 0 ,
                 books : HashMap::new(),
                 event_publisher  ,
+                seq : 0 ,
+                symbol_seqs : HashMap::new(),
+                input : InputContext::default(),
+                next_input_position : 0 ,
+                trade_id : 0 ,
+                order_ids : OrderIds::new(),
+                executions : ExecutionTracker::new(),
+                stats : MarketStats::default(),
+                input_journal : None ,
+                replaying : false ,
             } 
             
     }
+    // a book set up from reference data , replaces any book already trading the symbol 
+    pub fn add_instrument(&mut self , instrument : Instrument){
+        let symbol = instrument.symbol;
+        if self.books.insert(symbol, OrderBook::with_instrument(instrument)).is_none(){
+            self.book_count = self.book_count.saturating_add(1);
+        }
+    }
+
+    pub fn load_instruments(&mut self , registry : &InstrumentRegistry){
+        for instrument in registry.instruments.values(){
+            self.add_instrument(instrument.clone());
+        }
+    }
+
+    // for callers without a queue , the message goes right after the previous one 
+    pub fn process_order(&mut self , shm_order : ShmOrder){
+        self.process_order_at(shm_order, self.next_input_position);
+    }
+
+    // `input_position` is where the record sat in the input queue 
+    pub fn process_order_at(&mut self , shm_order : ShmOrder , input_position : u64){
+        self.process_input(shm_order, input_position, now_ns());
+    }
+
+    // a record taken off the input queue , journaled first when there is a journal 
+    // a record the journal could not take is not processed , a run the journal cannot replay would be worse than
+    // stopping . that includes a position at or before the journal's last one , a queue recreated under a journal
+    // that already holds positions needs a fresh journal directory 
+    pub fn accept_input(&mut self , shm_order : ShmOrder , input_position : u64)->Result<() , JournalError>{
+        let received_at = now_ns();
+        if let Some(journal) = &mut self.input_journal{
+            journal.record(input_position, received_at, &shm_order)?;
+        }
+        self.process_input(shm_order, input_position, received_at);
+        Ok(())
+    }
+
+    // one input of an earlier run , stamped the way that run stamped it 
+    pub fn replay_input(&mut self , input : &JournaledInput){
+        self.process_input(input.order, input.position, input.received_at);
+    }
+
+    // decodes one record from the queue and runs it against the book for its symbol 
+    fn process_input(&mut self , shm_order : ShmOrder , input_position : u64 , received_at : u64){
+        self.input = InputContext { position : input_position , symbol : shm_order.symbol , timestamp : shm_order.timestamp , received_at };
+        self.next_input_position = input_position + 1;
+        // time moved on , bars that ended before this message close first . bars run on the engine's clock ,
+        // a client's timestamp can be anything 
+        for bar in self.stats.on_time(received_at) {
+            self.emit_for(bar.symbol, Event::BarClosed(bar));
+        }
+        // a volatility auction that ran its course uncrosses before the message is looked at 
+        self.end_volatility_auction(shm_order.symbol, received_at);
+        match shm_order.msg_type {
+            ShmOrder::NEW => self.process_new_order(shm_order),
+            ShmOrder::CANCEL => self.process_cancel(shm_order),
+            ShmOrder::AMEND => self.process_amend(shm_order),
+            ShmOrder::SESSION => self.process_session(shm_order),
+            msg_type => self.reject(shm_order, None, OrderBookError::InvalidField { field : "msg_type" , value : msg_type })
+        }
+        // trades printed above may have fired resting stops 
+        self.trigger_stops(shm_order);
+        self.publish_indication(shm_order.symbol);
+        self.publish_level_updates(shm_order.symbol);
+        // orders that are done free their client ids 
+        for order_id in self.executions.take_closed() {
+            self.order_ids.forget(order_id);
+        }
+    }
+
+    // one L2 update per level the message changed , after everything it set off , then the top if it moved 
+    fn publish_level_updates(&mut self , symbol : u32){
+        let (updates , bbo) = match self.books.get_mut(&symbol) {
+            Some(order_book) => (order_book.take_level_updates() , order_book.take_bbo_change()),
+            None => return
+        };
+        for update in updates {
+            self.emit(Event::PriceLevelChangedEvent(update));
+        }
+        if let Some(bbo) = bbo {
+            self.emit(Event::BboChanged(bbo));
+        }
+    }
+
+    fn decode_side(value : u8)->Result<Side , OrderBookError>{
+        match value {
+            0 => Ok(Side::Bid),
+            1 => Ok(Side::Ask),
+            value => Err(OrderBookError::InvalidField { field : "side" , value })
+        }
+    }
+
+    // turns a queue record into an order , anything that does not decode is rejected 
+    fn decode_order(shm_order : ShmOrder)->Result<Order , OrderBookError>{
+        let order_side = Self::decode_side(shm_order.side)?;
+        let order_type = match shm_order.order_type {
+            0 => Type::Limit,
+            1 => Type::Market,
+            2 => Type::Stop,
+            3 => Type::StopLimit,
+            value => return Err(OrderBookError::InvalidField { field : "order_type" , value })
+        };
+        let time_in_force = match shm_order.time_in_force {
+            0 => TimeInForce::Gtc,
+            1 => TimeInForce::Ioc,
+            2 => TimeInForce::Fok,
+            value => return Err(OrderBookError::InvalidField { field : "time_in_force" , value })
+        };
+        let post_only = match (shm_order.post_only , order_type) {
+            (0 , _) => PostOnly::Off,
+            (1 , Type::Limit) => PostOnly::Reject,
+            (2 , Type::Limit) => PostOnly::Slide,
+            // a market or stop order is always a taker
+            (value , _) => return Err(OrderBookError::InvalidField { field : "post_only" , value })
+        };
+        let mut my_order = match order_type {
+            Type::Limit if shm_order.display_qty > 0 => Order::iceberg(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.display_qty, shm_order.price, shm_order.timestamp, shm_order.symbol),
+            Type::Limit => Order::new(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.price, shm_order.timestamp, shm_order.symbol),
+            Type::Market => Order::market(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.timestamp, shm_order.symbol),
+            Type::Stop => Order::stop(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.stop_price, shm_order.timestamp, shm_order.symbol),
+            Type::StopLimit => Order::stop_limit(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.price, shm_order.stop_price, shm_order.timestamp, shm_order.symbol)
+        };
+        // a market order can never rest , so gtc on the wire keeps its default of ioc
+        if !(matches!(order_type , Type::Market | Type::Stop) && time_in_force == TimeInForce::Gtc){
+            my_order.time_in_force = time_in_force;
+        }
+        my_order.post_only = post_only;
+        my_order.client_id = shm_order.client_id;
+        Ok(my_order)
+    }
+
+    fn process_new_order(&mut self , shm_order : ShmOrder){
+        let mut my_order = match Self::decode_order(shm_order) {
+            Ok(order) => order,
+            Err(reason) => return self.reject(shm_order, None, reason)
+        };
+        if !self.books.contains_key(&my_order.symbol){
+            return self.reject(shm_order, None, OrderBookError::UnknownSymbol(shm_order.symbol));
+        }
+        my_order.order_id = match self.order_ids.assign(shm_order.client_id, shm_order.order_id) {
+            Ok(order_id) => order_id,
+            Err(reason) => return self.reject(shm_order, None, reason)
+        };
+        if self.order_ids.scoped {
+            self.emit(Event::OrderIdAssigned(OrderIdAssigned { client_id : shm_order.client_id , client_order_id : shm_order.order_id , order_id : my_order.order_id }));
+        }
+        let incoming = my_order;
+        let order_book = self.books.get_mut(&my_order.symbol).unwrap();
+        let events = match (my_order.order_type , my_order.side) {
+            (Type::Market , _) => order_book.match_market_order(&mut my_order),
+            (Type::Limit , Side::Bid) => order_book.match_bid(&mut my_order),
+            (Type::Limit , Side::Ask) => order_book.match_ask(&mut my_order),
+            (Type::Stop | Type::StopLimit , _) => {
+                order_book.add_stop_order(my_order)
+                    .map(|()| MatchResult::new(my_order.order_id, my_order.shares_qty))
+            }
+        };
+        match events {
+            Ok(match_result) => {
+                let accepted = self.executions.accepted(&incoming);
+                self.emit(Event::ExecutionReport(accepted));
+                self.publish_match(match_result);
+            }
+            Err(reason) => {
+                // the book never took it , the id it was given is free again
+                self.order_ids.forget(my_order.order_id);
+                self.reject(shm_order, None, reason)
+            }
+        }
+    }
+
+    fn process_cancel(&mut self , shm_order : ShmOrder){
+        let order_id = match self.order_ids.resolve(shm_order.client_id, shm_order.order_id) {
+            Ok(order_id) => order_id,
+            Err(reason) => return self.reject(shm_order, None, reason)
+        };
+        let Some(order_book) = self.books.get_mut(&shm_order.symbol) else {
+            return self.reject(shm_order, Some(order_id), OrderBookError::UnknownSymbol(shm_order.symbol));
+        };
+        match order_book.cancel_order(order_id) {
+            Some(order) => {
+                self.emit(Event::OrderCancelled(OrderCancelled::new(&order)));
+                if let Some(report) = self.executions.cancelled(order_id) {
+                    self.emit(Event::ExecutionReport(report));
+                }
+            }
+            None => self.reject(shm_order, None, OrderBookError::OrderNotFound(shm_order.order_id))
+        }
+    }
+
+    // `live_order` is the book's id for the order a refused cancel or amend was aimed at 
+    fn reject(&mut self , shm_order : ShmOrder , live_order : Option<OrderId> , reason : OrderBookError){
+        // a side byte that does not decode is not reported as either side 
+        let side = Self::decode_side(shm_order.side).ok();
+        let report = self.executions.rejected(shm_order.order_id, live_order, shm_order.client_id, shm_order.symbol, side, reason);
+        self.emit(Event::OrderRejected(OrderRejected::new(shm_order.order_id, reason)));
+        self.emit(Event::ExecutionReport(report));
+    }
+
+    // price and shares_qty of the record are the new values for the order
+    fn process_amend(&mut self , shm_order : ShmOrder){
+        let order_id = match self.order_ids.resolve(shm_order.client_id, shm_order.order_id) {
+            Ok(order_id) => order_id,
+            Err(reason) => return self.reject(shm_order, None, reason)
+        };
+        let Some(order_book) = self.books.get_mut(&shm_order.symbol) else {
+            return self.reject(shm_order, Some(order_id), OrderBookError::UnknownSymbol(shm_order.symbol));
+        };
+        match order_book.amend_order(order_id, shm_order.price, shm_order.shares_qty) {
+            Ok((amended , match_result)) => {
+                let replaced = self.executions.replaced(&amended);
+                self.emit(Event::OrderAmended(amended));
+                if let Some(report) = replaced {
+                    self.emit(Event::ExecutionReport(report));
+                }
+                if let Some(match_result) = match_result {
+                    self.publish_match(match_result);
+                }
+            }
+            Err(reason) => self.reject(shm_order, Some(order_id), reason)
+        }
+    }
+
+    // session_state of the record is the phase the book moves to 
+    // a refused session message has no order behind it , only the rejection goes out 
+    fn process_session(&mut self , shm_order : ShmOrder){
+        let Some(next) = SessionState::from_u8(shm_order.session_state) else {
+            let reason = OrderBookError::InvalidField { field : "session_state" , value : shm_order.session_state };
+            return self.emit(Event::OrderRejected(OrderRejected::new(shm_order.order_id, reason)));
+        };
+        let Some(order_book) = self.books.get_mut(&shm_order.symbol) else {
+            return self.emit(Event::OrderRejected(OrderRejected::new(shm_order.order_id, OrderBookError::UnknownSymbol(shm_order.symbol))));
+        };
+        let from = order_book.session;
+        match order_book.transition(next) {
+            Ok(uncross) => {
+                self.emit(Event::SessionChanged(SessionChanged { symbol : shm_order.symbol , from , to : next }));
+                // a new trading day , yesterday's figures and whatever bars are left go out first 
+                if from == SessionState::Closed && next == SessionState::PreOpen {
+                    for bar in self.stats.reset(shm_order.symbol) {
+                        self.emit(Event::BarClosed(bar));
+                    }
+                }
+                if let Some(result) = uncross {
+                    self.publish_uncross(result);
+                }
+            }
+            Err(reason) => self.emit(Event::OrderRejected(OrderRejected::new(shm_order.order_id, reason)))
+        }
+    }
+
+    // stamps and sends , a closed channel only means nobody is listening any more 
+    fn emit(&mut self , event : Event){
+        self.emit_for(self.input.symbol, event);
+    }
+
+    // for events about another symbol than the message , a bar closed by the clock 
+    fn emit_for(&mut self , symbol : u32 , event : Event){
+        self.seq += 1;
+        let symbol_seq = self.symbol_seqs.entry(symbol).or_insert(0);
+        *symbol_seq += 1;
+        let _ = self.event_publisher.send(SequencedEvent{
+            engine_id : self.engine_id ,
+            seq : self.seq ,
+            symbol ,
+            symbol_seq : *symbol_seq ,
+            input_position : self.input.position ,
+            received_at : self.input.received_at ,
+            emitted_at : if self.replaying { self.input.received_at } else { now_ns() } ,
+            event
+        });
+    }
+
+    // one print per fill for the time and sales feed 
+    fn publish_trades(&mut self , fills : &Fills){
+        for fill in &fills.fills {
+            self.trade_id += 1;
+            let trade = Trade{
+                trade_id : self.trade_id ,
+                symbol : self.input.symbol ,
+                aggressor_side : fill.aggressor_side ,
+                price : fill.price ,
+                quantity : fill.quantity ,
+                timestamp : self.input.received_at
+            };
+            self.emit(Event::Trade(trade));
+            for bar in self.stats.on_trade(&trade) {
+                self.emit(Event::BarClosed(bar));
+            }
+        }
+    }
+
+    fn publish_match(&mut self , match_result : MatchResult){
+        let interruption = match_result.volatility_interruption;
+        let reports = self.executions.matched(&match_result);
+        self.publish_trades(&match_result.fills);
+        self.emit(Event::MatchResult(match_result));
+        for report in reports {
+            self.emit(Event::ExecutionReport(report));
+        }
+        if let Some(interruption) = interruption {
+            self.emit(Event::VolatilityInterruption(interruption));
+        }
+    }
+
+    fn publish_uncross(&mut self , result : AuctionResult){
+        let reports = self.executions.uncrossed(&result);
+        self.publish_trades(&result.fills);
+        self.emit(Event::AuctionUncrossed(result));
+        for report in reports {
+            self.emit(Event::ExecutionReport(report));
+        }
+    }
+
+    fn end_volatility_auction(&mut self , symbol : u32 , received_at : u64){
+        if let Some(order_book) = self.books.get_mut(&symbol)
+            && let Some(result) = order_book.on_time(received_at){
+                self.publish_uncross(result);
+        }
+    }
+
+    // while a book is in an auction call every message can move the indicative uncross 
+    fn publish_indication(&mut self , symbol : u32){
+        if let Some(order_book) = self.books.get(&symbol)
+            && order_book.auction.is_some()
+            && let Some(indication) = order_book.indicative_uncross(order_book.reference_price()){
+                self.emit(Event::AuctionIndication(indication));
+        }
+    }
+
+    fn trigger_stops(&mut self , shm_order : ShmOrder){
+        let Some(order_book) = self.books.get_mut(&shm_order.symbol) else {
+            return;
+        };
+        for (trigger , match_result) in order_book.trigger_stops() {
+            let order_id = trigger.order_id;
+            self.emit(Event::StopTriggered(trigger));
+            match match_result {
+                Ok(match_result) => self.publish_match(match_result),
+                // the stop left the trigger book but could not trade , it is done
+                Err(reason) => {
+                    self.emit(Event::OrderRejected(OrderRejected::new(order_id, reason)));
+                    if let Some(report) = self.executions.killed(order_id, reason) {
+                        self.emit(Event::ExecutionReport(report));
+                    }
+                }
+            }
+        }
+    }
+
     pub fn run_engine(&mut self ){
         // the queue struct (shared memory file will be initialised by the producer )
         // we need to initlaise a queue struct here and then start listening to it in an infinite loop
@@ -54,31 +453,10 @@ impl MyEngine{
         loop {
             match queue.dequeue() {
                 Ok(Some(shm_order))=>{
-                    
-                    //println!("got the shm order");
-                    let order_side = match  shm_order.side {
-                        0 => {
-                            Side::Bid
-                        },
-                        1=>{
-                            Side::Ask
-                        }
-                        _ => {
-                            continue;
-                        }
-                    };
-                    let mut my_order = Order::new(shm_order.order_id, order_side, shm_order.shares_qty, shm_order.price, shm_order.timestamp, shm_order.symbol);
-                    
-                    if let Some(order_book) = self.get_book_mut(my_order.symbol){
-                        let events = match order_side {
-                            Side::Bid => order_book.match_bid(&mut my_order),
-                            Side::Ask => order_book.match_ask(&mut my_order)
-                        };
-                        //println!("{:?}" , events);
-                        if let Ok(match_result)=events{
-                            let _ = self.event_publisher.send(Event::MatchResult(match_result));
-                        }
-                       
+                    // the queue has moved past the record we just took 
+                    if let Err(e) = self.accept_input(shm_order, queue.consumed() - 1){
+                        eprintln!("input {} not journaled , stopping {}" , queue.consumed() - 1 , e);
+                        return;
                     }
                     count+=1;
                     if last_log.elapsed().as_secs() >= 2 {
@@ -91,6 +469,12 @@ impl MyEngine{
                 }
                 Ok(None)=>{
                     //println!("order not reiceved");
+                    // a quiet moment , hand what was journaled to the os 
+                    if let Some(journal) = &mut self.input_journal
+                        && let Err(e) = journal.commit(){
+                            eprintln!("input journal commit failed , stopping {}" , e);
+                            return;
+                    }
                 }
                 Err(_)=>{
                     println!("Some errorr");
@@ -108,7 +492,7 @@ impl Engine for MyEngine{
         self.book_count = self.book_count.saturating_add(1);
     }
     fn get_book(&self , symbol : u32)->Option<&OrderBook> {
-       self.books.get(&symbol).map(|orderbook| orderbook)
+       self.books.get(&symbol)
     }
     fn get_book_mut(&mut self, symbol: u32) -> Option<&mut OrderBook> {
         self.books.get_mut(&symbol)
@@ -129,4 +513,461 @@ impl Engine for MyEngine{
     }
     
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::types::{ExecType, ExecutionReport, Liquidity, PriceLevelChangedEvent};
+
+    fn events(rx : &crossbeam::channel::Receiver<SequencedEvent>) -> impl Iterator<Item = Event> + '_ {
+        rx.try_iter().map(|sequenced| sequenced.event)
+    }
+
+    // the next event that is not an execution report or market data , those have their own tests
+    fn next_event(rx : &crossbeam::channel::Receiver<SequencedEvent>) -> Option<Event> {
+        events(rx).find(|event| !matches!(event , Event::ExecutionReport(_) | Event::PriceLevelChangedEvent(_) | Event::BboChanged(_) | Event::Trade(_) | Event::BarClosed(_)))
+    }
+
+    fn reports(rx : &crossbeam::channel::Receiver<SequencedEvent>) -> Vec<ExecutionReport> {
+        events(rx).filter_map(|event| match event {
+            Event::ExecutionReport(report) => Some(report),
+            _ => None
+        }).collect()
+    }
+
+    fn shm_order(order_id : u64 , side : u8 , order_type : u8 , qty : u32 , price : u64) -> ShmOrder {
+        ShmOrder { order_id , side , order_type , shares_qty : qty , price , symbol : 0 , ..Default::default() }
+    }
+
+    #[test]
+    fn test_market_order_dispatch() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+
+        engine.process_order(shm_order(1, 1, 0, 50, 100));
+        let _ = next_event(&rx).unwrap();
+
+        // market bid larger than the book , price field is ignored
+        engine.process_order(shm_order(2, 0, 1, 80, 1));
+        match next_event(&rx).unwrap() {
+            Event::MatchResult(result) => {
+                assert_eq!(result.fills.fills.len(), 1);
+                assert_eq!(result.fills.fills[0].price, 100);
+                assert_eq!(result.cancelled_qty, 30);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        let book = engine.get_book_mut(0).unwrap();
+        assert_eq!(book.get_best_bid(), None);
+        assert_eq!(book.get_best_ask(), None);
+
+        // unknown order type is rejected with the field that did not decode
+        engine.process_order(shm_order(3, 0, 9, 10, 100));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => {
+                assert_eq!(rejected.order_id, 3);
+                assert_eq!(rejected.reason, OrderBookError::InvalidField { field: "order_type", value: 9 });
+                assert_eq!(rejected.reason_code, 6);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cancel_and_amend_messages() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.add_book(1);
+
+        engine.process_order(shm_order(1, 0, 0, 50, 100));
+        let _ = next_event(&rx).unwrap();
+
+        engine.process_order(ShmOrder::amend(1, 0, 0, 100, 30, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderAmended(amended) => {
+                assert!(amended.priority_kept);
+                assert_eq!(amended.new_qty, 30);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // wrong symbol does not touch book 0
+        engine.process_order(ShmOrder::cancel(1, 1, 0, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::OrderNotFound(1)),
+            other => panic!("unexpected event {:?}", other),
+        }
+        engine.process_order(ShmOrder::cancel(1, 9, 0, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::UnknownSymbol(9)),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        engine.process_order(ShmOrder::cancel(1, 0, 0, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderCancelled(cancelled) => {
+                assert_eq!(cancelled.order_id, 1);
+                assert_eq!(cancelled.cancelled_qty, 30);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(engine.get_book_mut(0).unwrap().get_best_bid(), None);
+    }
+
+    #[test]
+    fn test_session_messages() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+
+        engine.process_order(ShmOrder::session(0, 3, 0));
+        match next_event(&rx).unwrap() {
+            Event::SessionChanged(changed) => {
+                assert_eq!(changed.from, SessionState::Continuous);
+                assert_eq!(changed.to, SessionState::Halted);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // halted book rejects the order instead of resting it
+        engine.process_order(shm_order(1, 0, 0, 50, 100));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.order_id, 1),
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(engine.get_book_mut(0).unwrap().get_best_bid(), None);
+
+        // halted to pre close is not a legal move , the message is rejected
+        engine.process_order(ShmOrder::session(0, 2, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::IllegalTransition { from: SessionState::Halted, to: SessionState::PreClose }),
+            other => panic!("unexpected event {:?}", other),
+        }
+        engine.process_order(ShmOrder::session(0, 9, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::InvalidField { field: "session_state", value: 9 }),
+            other => panic!("unexpected event {:?}", other),
+        }
+        engine.process_order(ShmOrder { msg_type : 7 , ..shm_order(2, 0, 0, 50, 100) });
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::InvalidField { field: "msg_type", value: 7 }),
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(next_event(&rx).is_none());
+    }
+
+    #[test]
+    fn test_books_from_registry() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        let registry = InstrumentRegistry::parse("[0]\nname = BTCUSD\ntick_size = 5\n[7]\nname = ETHUSD").unwrap();
+        engine.load_instruments(&registry);
+        assert_eq!(engine.get_book_count(), 2);
+        assert_eq!(engine.get_book(0).unwrap().instrument.name, "BTCUSD");
+
+        engine.process_order(shm_order(1, 0, 0, 50, 103));
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert!(matches!(rejected.reason, OrderBookError::OffTick { .. })),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_client_scoped_order_ids() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.order_ids = OrderIds::scoped();
+        engine.add_book(0);
+
+        // two clients both use order id 1 , the books see exchange ids 1 and 2
+        for (client_id , price) in [(7 , 100) , (8 , 101)] {
+            engine.process_order(ShmOrder { client_id , ..shm_order(1, 1, 0, 10, price) });
+        }
+        let assigned : Vec<OrderIdAssigned> = events(&rx).filter_map(|event| match event {
+            Event::OrderIdAssigned(assigned) => Some(assigned),
+            _ => None
+        }).collect();
+        assert_eq!(assigned.iter().map(|a| (a.client_id , a.order_id)).collect::<Vec<_>>(), vec![(7 , 1) , (8 , 2)]);
+
+        // a live id is refused for the same client
+        engine.process_order(ShmOrder { client_id : 7 , ..shm_order(1, 1, 0, 10, 102) });
+        match next_event(&rx).unwrap() {
+            Event::OrderRejected(rejected) => assert_eq!(rejected.reason, OrderBookError::DuplicateOrderId(1)),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // client 8 cancels its own order 1 , client 7's order stays
+        engine.process_order(ShmOrder::cancel(1, 0, 8, 0));
+        match next_event(&rx).unwrap() {
+            Event::OrderCancelled(cancelled) => assert_eq!(cancelled.order_id, 2),
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(engine.get_book_mut(0).unwrap().get_best_ask(), Some(100));
+
+        // filled , amended to zero and refused orders all give their ids back
+        engine.process_order(ShmOrder { client_id : 9 , ..shm_order(5, 0, 0, 10, 100) });
+        engine.process_order(ShmOrder { client_id : 9 , ..shm_order(6, 0, 0, 10, 90) });
+        engine.process_order(ShmOrder::amend(6, 0, 9, 90, 0, 0));
+        engine.process_order(ShmOrder { client_id : 9 , ..shm_order(7, 0, 0, 10, 0) });
+        assert!(engine.order_ids.is_empty());
+        events(&rx).for_each(drop);
+        engine.process_order(ShmOrder { client_id : 7 , ..shm_order(1, 1, 0, 10, 102) });
+        match next_event(&rx).unwrap() {
+            Event::OrderIdAssigned(assigned) => assert_eq!((assigned.client_order_id , assigned.order_id) , (1 , 6)),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_execution_reports() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+
+        engine.process_order(ShmOrder { client_id : 1 , ..shm_order(1, 1, 0, 30, 100) });
+        engine.process_order(ShmOrder { client_id : 2 , ..shm_order(2, 1, 0, 15, 101) });
+        engine.process_order(ShmOrder { client_id : 4 , ..shm_order(4, 1, 0, 30, 102) });
+        // ioc bid for 50 takes the 30 at 100 and 15 at 101 , nothing above its limit
+        engine.process_order(ShmOrder { client_id : 3 , time_in_force : 1 , ..shm_order(3, 0, 0, 50, 101) });
+        engine.process_order(ShmOrder::amend(4, 0, 4, 102, 5, 0));
+        engine.process_order(ShmOrder::cancel(4, 0, 4, 0));
+        engine.process_order(ShmOrder::cancel(4, 0, 4, 0));
+
+        let summary : Vec<(OrderId , ExecType , u32 , u32)> = reports(&rx).iter().map(|r| (r.order_id , r.exec_type , r.cum_qty , r.leaves_qty)).collect();
+        assert_eq!(summary, vec![
+            (1 , ExecType::New , 0 , 30),
+            (2 , ExecType::New , 0 , 15),
+            (4 , ExecType::New , 0 , 30),
+            (3 , ExecType::New , 0 , 50),
+            (3 , ExecType::PartialFill , 30 , 20),
+            (1 , ExecType::Fill , 30 , 0),
+            (3 , ExecType::PartialFill , 45 , 5),
+            (2 , ExecType::Fill , 15 , 0),
+            (3 , ExecType::Expired , 45 , 0),
+            (4 , ExecType::Replaced , 0 , 5),
+            (4 , ExecType::Cancelled , 0 , 0),
+            (4 , ExecType::Rejected , 0 , 0),
+        ]);
+        assert!(engine.executions.is_empty());
+
+        // a rejection reports the side the message gave , or none when the byte is not a side
+        engine.process_order(shm_order(5, 1, 0, 0, 100));
+        engine.process_order(shm_order(6, 7, 0, 10, 100));
+        let sides : Vec<(OrderId , Option<Side>)> = reports(&rx).iter().map(|r| (r.order_id , r.side)).collect();
+        assert_eq!(sides, vec![(5 , Some(Side::Ask)) , (6 , None)]);
+    }
+
+    #[test]
+    fn test_maker_reports_from_resting_side() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.process_order(ShmOrder { client_id : 1 , ..shm_order(1, 1, 0, 10, 100) });
+        engine.process_order(ShmOrder { client_id : 2 , ..shm_order(2, 0, 1, 4, 0) });
+        let maker = reports(&rx).into_iter().find(|r| r.order_id == 1 && r.exec_type == ExecType::PartialFill).unwrap();
+        assert_eq!((maker.client_id , maker.side , maker.liquidity) , (1 , Some(Side::Ask) , Some(Liquidity::Maker)));
+        assert_eq!((maker.last_price , maker.last_qty , maker.avg_price) , (100 , 4 , 100.0));
+    }
+
+    #[test]
+    fn test_decrement_and_cancel_reports_both_sides() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.get_book_mut(0).unwrap().stp_policy = crate::orderbook::types::StpPolicy::DecrementAndCancel;
+        engine.process_order(ShmOrder { client_id : 1 , ..shm_order(1, 1, 0, 30, 100) });
+        // the bid is used up , the resting ask keeps 20
+        engine.process_order(ShmOrder { client_id : 1 , ..shm_order(2, 0, 0, 10, 100) });
+        // the ask is used up , the bid rests with 30
+        engine.process_order(ShmOrder { client_id : 1 , ..shm_order(3, 0, 0, 50, 100) });
+
+        let summary : Vec<(OrderId , ExecType , u32 , u32)> = reports(&rx).iter().map(|r| (r.order_id , r.exec_type , r.order_qty , r.leaves_qty)).collect();
+        assert_eq!(summary, vec![
+            (1 , ExecType::New , 30 , 30),
+            (2 , ExecType::New , 10 , 10),
+            (1 , ExecType::Restated , 20 , 20),
+            (2 , ExecType::Cancelled , 0 , 0),
+            (3 , ExecType::New , 50 , 50),
+            (1 , ExecType::Cancelled , 0 , 0),
+            (3 , ExecType::Restated , 30 , 30),
+        ]);
+        assert_eq!(engine.executions.len(), 1);
+        assert_eq!(engine.get_book_mut(0).unwrap().get_best_bid(), Some(100));
+    }
+
+    #[test]
+    fn test_level_updates_coalesced_per_message() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.process_order(shm_order(1, 1, 0, 10, 100));
+        engine.process_order(shm_order(2, 1, 0, 10, 100));
+        engine.process_order(shm_order(3, 1, 0, 10, 101));
+        let _ = events(&rx).count();
+
+        // one market order fills two orders at 100 and part of 101 , one update per level
+        engine.process_order(shm_order(4, 0, 1, 25, 0));
+        let updates : Vec<PriceLevelChangedEvent> = events(&rx).filter_map(|event| match event {
+            Event::PriceLevelChangedEvent(update) => Some(update),
+            _ => None
+        }).collect();
+        assert_eq!(updates.len(), 2);
+        assert_eq!((updates[0].price , updates[0].quantity , updates[0].order_count) , (100 , 0 , 0));
+        assert_eq!((updates[1].price , updates[1].quantity , updates[1].order_count) , (101 , 5 , 1));
+        assert_eq!(updates[1].side, Side::Ask);
+    }
+
+    #[test]
+    fn test_events_are_sequenced() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 3);
+        engine.add_book(0);
+        engine.add_book(1);
+        engine.process_order(shm_order(1, 1, 0, 10, 100));
+        engine.process_order(ShmOrder { symbol : 1 , ..shm_order(2, 1, 0, 10, 100) });
+        engine.process_order_at(shm_order(3, 0, 0, 4, 100), 41);
+
+        let sequenced : Vec<SequencedEvent> = rx.try_iter().collect();
+        // engine wide numbers have no gaps
+        assert!(sequenced.iter().enumerate().all(|(i , event)| event.seq == i as u64 + 1 && event.engine_id == 3));
+        // each symbol counts on its own
+        let symbol_seqs : Vec<u64> = sequenced.iter().filter(|event| event.symbol == 0).map(|event| event.symbol_seq).collect();
+        assert_eq!(symbol_seqs, (1..=symbol_seqs.len() as u64).collect::<Vec<_>>());
+        assert_eq!(sequenced.iter().filter(|event| event.symbol == 1).map(|event| event.symbol_seq).max(), Some(sequenced.iter().filter(|event| event.symbol == 1).count() as u64));
+        // input positions follow the messages , an explicit position is kept
+        let positions : Vec<u64> = sequenced.iter().map(|event| event.input_position).collect();
+        assert_eq!(positions.first(), Some(&0));
+        assert!(positions.contains(&1));
+        assert_eq!(positions.last(), Some(&41));
+        assert!(sequenced.iter().all(|event| event.emitted_at >= event.received_at));
+    }
+
+    #[test]
+    fn test_bbo_event_only_on_change() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        let bbo_events = |rx : &crossbeam::channel::Receiver<SequencedEvent>| events(rx).filter(|event| matches!(event , Event::BboChanged(_))).count();
+
+        engine.process_order(shm_order(1, 1, 0, 10, 100));
+        assert_eq!(bbo_events(&rx), 1);
+        // deeper ask , top unchanged
+        engine.process_order(shm_order(2, 1, 0, 10, 105));
+        assert_eq!(bbo_events(&rx), 0);
+        engine.process_order(shm_order(3, 0, 0, 10, 99));
+        assert_eq!(bbo_events(&rx), 1);
+        assert_eq!(engine.get_book(0).unwrap().get_bbo().bid_price, Some(99));
+    }
+
+    #[test]
+    fn test_trade_prints() {
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.add_book(1);
+        engine.process_order(shm_order(1, 1, 0, 10, 100));
+        engine.process_order(shm_order(2, 1, 0, 10, 101));
+        engine.process_order(ShmOrder { symbol : 1 , ..shm_order(3, 0, 0, 5, 50) });
+        // stamped with when the engine took the message
+        engine.process_input(shm_order(4, 0, 1, 15, 0), 3, 77);
+        engine.process_input(ShmOrder { symbol : 1 , ..shm_order(5, 1, 1, 5, 0) }, 4, 78);
+
+        let trades : Vec<Trade> = events(&rx).filter_map(|event| match event {
+            Event::Trade(trade) => Some(trade),
+            _ => None
+        }).collect();
+        assert_eq!(trades.len(), 3);
+        // ids run across books
+        assert_eq!(trades.iter().map(|t| t.trade_id).collect::<Vec<_>>(), vec![1 , 2 , 3]);
+        assert_eq!((trades[0].price , trades[0].quantity , trades[0].aggressor_side , trades[0].timestamp) , (100 , 10 , Some(Side::Bid) , 77));
+        assert_eq!((trades[1].price , trades[1].quantity) , (101 , 5));
+        assert_eq!((trades[2].symbol , trades[2].aggressor_side , trades[2].timestamp) , (1 , Some(Side::Ask) , 78));
+    }
+
+    #[test]
+    fn test_bars_and_session_stats() {
+        use crate::engine::market_stats::ONE_SECOND;
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.add_book(1);
+        // the client timestamps say nothing , the engine's clock decides which bar a trade is in
+        engine.process_input(shm_order(1, 1, 0, 10, 100), 0, 1);
+        engine.process_input(shm_order(2, 1, 0, 10, 102), 1, 2);
+        engine.process_input(ShmOrder { timestamp : 5 * ONE_SECOND , ..shm_order(3, 0, 1, 10, 0) }, 2, 10);
+        engine.process_input(ShmOrder { timestamp : 5 * ONE_SECOND , ..shm_order(4, 0, 1, 10, 0) }, 3, 20);
+
+        let stats = engine.stats.get(0).unwrap();
+        assert_eq!((stats.open , stats.high , stats.low , stats.close , stats.volume) , (100 , 102 , 100 , 102 , 20));
+        assert_eq!(stats.vwap(), Some(101.0));
+        let trades : Vec<u64> = events(&rx).filter_map(|event| match event {
+            Event::Trade(trade) => Some(trade.timestamp),
+            Event::BarClosed(_) => panic!("no bar has ended yet"),
+            _ => None
+        }).collect();
+        assert_eq!(trades, vec![10 , 20]);
+
+        // a message on another symbol a second later still closes the 1s bar of symbol 0
+        engine.process_input(ShmOrder { symbol : 1 , ..shm_order(5, 0, 0, 1, 50) }, 4, ONE_SECOND);
+        let sequenced : Vec<SequencedEvent> = rx.try_iter().collect();
+        let bars : Vec<_> = sequenced.iter().filter_map(|sequenced| match sequenced.event {
+            Event::BarClosed(bar) => Some((sequenced.symbol , bar)),
+            _ => None
+        }).collect();
+        assert_eq!(bars.len(), 1);
+        let (symbol , bar) = bars[0];
+        assert_eq!((symbol , bar.interval , bar.start , bar.open , bar.close , bar.volume , bar.trade_count) , (0 , ONE_SECOND , 0 , 100 , 102 , 20 , 2));
+    }
+
+    #[test]
+    fn test_volatility_auction_runs_on_receive_time() {
+        use crate::orderbook::price_band::PriceBands;
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.books.get_mut(&0).unwrap().bands = PriceBands { dynamic_bps : 500 , static_reference : Some(100) , volatility_auction_period : 1_000 , ..Default::default() };
+        engine.process_input(shm_order(1, 1, 0, 10, 100), 0, 1);
+        engine.process_input(shm_order(2, 1, 0, 10, 120), 1, 2);
+        // a small client timestamp does not put the end of the auction in the past
+        engine.process_input(ShmOrder { timestamp : 0 , ..shm_order(3, 0, 1, 20, 0) }, 2, 2_000);
+        let ends_at = events(&rx).find_map(|event| match event {
+            Event::VolatilityInterruption(interruption) => Some(interruption.ends_at),
+            _ => None
+        });
+        assert_eq!(ends_at, Some(3_000));
+
+        // nor does a huge one end it early
+        engine.process_input(ShmOrder { timestamp : u64::MAX , ..shm_order(4, 0, 0, 10, 120) }, 3, 2_500);
+        assert!(!events(&rx).any(|event| matches!(event , Event::AuctionUncrossed(_))));
+        engine.process_input(shm_order(5, 0, 0, 1, 90), 4, 3_000);
+        assert!(events(&rx).any(|event| matches!(event , Event::AuctionUncrossed(_))));
+    }
+
+    #[test]
+    fn test_input_the_journal_refuses_is_not_processed() {
+        use crate::journal::segment::{FsyncPolicy, JournalConfig};
+        let dir = std::env::temp_dir().join(format!("test_accept_input_{}" , std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        let config = JournalConfig { fsync : FsyncPolicy::Never , ..JournalConfig::default() };
+        engine.input_journal = Some(CommandJournal::open(&dir, config, 0).unwrap());
+        engine.accept_input(shm_order(1, 1, 0, 10, 100), 0).unwrap();
+        engine.accept_input(shm_order(2, 1, 0, 10, 101), 1).unwrap();
+        assert!(rx.try_iter().count() > 0);
+
+        // a recreated queue starts its positions over under the same journal
+        let e = engine.accept_input(shm_order(3, 0, 1, 10, 0), 0).unwrap_err();
+        assert!(matches!(e , JournalError::OutOfOrder { last : 1 , seq : 0 }));
+        assert_eq!(rx.try_iter().count(), 0);
+        assert_eq!(engine.books[&0].askside.best_level(), Some((100 , 10)));
+
+        // what was accepted is already with the os , a reader sees it without a sync
+        assert_eq!(crate::journal::command_journal::read_inputs(&dir, 0).unwrap().count(), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use crate::orderbook::types::{OrderBookError, OrderId};
+
+// maps the order ids clients send to the ids the books run on
+// unscoped every client shares one id space and ids pass through untouched , the book refuses a live duplicate
+// scoped each client_id has its own space and the engine hands out exchange ids , so two clients can both send order 1
+#[derive(Debug, Default)]
+pub struct OrderIds{
+    pub scoped : bool ,
+    next_exchange_id : OrderId ,
+    // (client_id , client order id) -> exchange id
+    to_exchange : HashMap<(u32 , OrderId) , OrderId> ,
+    to_client : HashMap<OrderId , (u32 , OrderId)>
+}
+
+impl OrderIds{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    pub fn scoped()->Self{
+        Self { scoped : true , next_exchange_id : 1 , ..Self::default() }
+    }
+
+    // id for a new order . a mapping lives until the engine forgets it when the order is done ,
+    // so one that is still here belongs to a live order
+    pub fn assign(&mut self , client_id : u32 , client_order_id : OrderId)->Result<OrderId , OrderBookError>{
+        if !self.scoped{
+            return Ok(client_order_id);
+        }
+        if self.to_exchange.contains_key(&(client_id , client_order_id)){
+            return Err(OrderBookError::DuplicateOrderId(client_order_id));
+        }
+        let exchange_id = self.next_exchange_id;
+        self.next_exchange_id += 1;
+        self.to_exchange.insert((client_id , client_order_id), exchange_id);
+        self.to_client.insert(exchange_id, (client_id , client_order_id));
+        Ok(exchange_id)
+    }
+
+    // exchange id a cancel or amend refers to , only the client that sent the order can reach it
+    pub fn resolve(&self , client_id : u32 , client_order_id : OrderId)->Result<OrderId , OrderBookError>{
+        if !self.scoped{
+            return Ok(client_order_id);
+        }
+        self.to_exchange.get(&(client_id , client_order_id)).copied().ok_or(OrderBookError::OrderNotFound(client_order_id))
+    }
+
+    // (client_id , client order id) behind an exchange id
+    pub fn client_order_id(&self , exchange_id : OrderId)->Option<(u32 , OrderId)>{
+        self.to_client.get(&exchange_id).copied()
+    }
+
+    pub fn forget(&mut self , exchange_id : OrderId){
+        if let Some(client_key) = self.to_client.remove(&exchange_id){
+            self.to_exchange.remove(&client_key);
+        }
+    }
+
+    pub fn len(&self)->usize{
+        self.to_client.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.to_client.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unscoped_ids_pass_through() {
+        let mut ids = OrderIds::new();
+        assert_eq!(ids.assign(7, 42), Ok(42));
+        assert_eq!(ids.resolve(8, 42), Ok(42));
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn test_scoped_ids_per_client() {
+        let mut ids = OrderIds::scoped();
+        assert_eq!(ids.assign(7, 1), Ok(1));
+        assert_eq!(ids.assign(8, 1), Ok(2));
+        assert_eq!(ids.resolve(8, 1), Ok(2));
+        assert_eq!(ids.client_order_id(1), Some((7, 1)));
+        // client 9 cannot reach another client's order
+        assert_eq!(ids.resolve(9, 1), Err(OrderBookError::OrderNotFound(1)));
+
+        assert_eq!(ids.assign(7, 1), Err(OrderBookError::DuplicateOrderId(1)));
+        // once the first order is forgotten the client id can be used again
+        ids.forget(1);
+        assert_eq!(ids.assign(7, 1), Ok(3));
+        assert_eq!(ids.client_order_id(1), None);
+        assert_eq!(ids.len(), 2);
+    }
+}
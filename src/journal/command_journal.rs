@@ -0,0 +1,140 @@
+use std::path::Path;
+use crate::journal::event_codec::{Decoder, DecodeError, Encoder};
+use crate::journal::segment::{JournalConfig, JournalError, JournalReader, JournalWriter, ReadError};
+use crate::orderbook::order::ShmOrder;
+
+// every message the engine took off its input queue , keyed by the queue position . each one is handed to the os
+// before it is processed , so it outlives the process crashing . it outlives the machine going down only once
+// synced , which the fsync policy decides : every record , or every interval at the cost of the last few .
+// a journal can span restarts , the first input of every run carries the engine seq that run started from
+pub const INPUT_PREFIX : &str = "inputs";
+
+// one input as the engine saw it
+#[derive(Debug, Clone, Copy)]
+pub struct JournaledInput{
+    pub position : u64 ,
+    // set on the first input after the engine started , the seq it started from . the engine before it is gone
+    pub run_start : Option<u64> ,
+    // wall clock when the engine took it , replay stamps events with this instead of reading the clock
+    pub received_at : u64 ,
+    pub order : ShmOrder
+}
+
+// field by field rather than the raw 64 bytes , the padding is not part of the record
+fn encode_input(run_start : Option<u64> , received_at : u64 , order : &ShmOrder , out : &mut Vec<u8>){
+    let mut encoder = Encoder::new(out);
+    encoder.option(run_start, Encoder::u64);
+    encoder.u64(received_at);
+    encoder.u64(order.order_id);
+    encoder.u64(order.price);
+    encoder.u64(order.timestamp);
+    encoder.u32(order.client_id);
+    encoder.u32(order.shares_qty);
+    encoder.u32(order.symbol);
+    encoder.bytes(&[order.side , order.status , order.order_type , order.time_in_force , order.post_only , order.msg_type , order.session_state]);
+    encoder.u64(order.stop_price);
+    encoder.u32(order.display_qty);
+}
+
+fn decode_input(position : u64 , payload : &[u8])->Result<JournaledInput , DecodeError>{
+    let mut decoder = Decoder::new(payload);
+    let run_start = decoder.option(Decoder::u64)?;
+    let received_at = decoder.u64()?;
+    let order_id = decoder.u64()?;
+    let price = decoder.u64()?;
+    let timestamp = decoder.u64()?;
+    let client_id = decoder.u32()?;
+    let shares_qty = decoder.u32()?;
+    let symbol = decoder.u32()?;
+    let [side , status , order_type , time_in_force , post_only , msg_type , session_state] = decoder.take::<7>()?;
+    let order = ShmOrder {
+        order_id , price , timestamp , client_id , shares_qty , symbol , side , status , order_type , time_in_force ,
+        post_only , msg_type , session_state ,
+        stop_price : decoder.u64()? ,
+        display_qty : decoder.u32()? ,
+        ..ShmOrder::default()
+    };
+    decoder.finish()?;
+    Ok(JournaledInput { position , run_start , received_at , order })
+}
+
+pub struct CommandJournal{
+    writer : JournalWriter ,
+    buf : Vec<u8> ,
+    // the seq this run started from until the first input has carried it 
+    run_start : Option<u64>
+}
+
+impl CommandJournal{
+    // every open is a new run of the engine , `start_seq` is the last seq it handed out before taking any input 
+    pub fn open(dir : impl AsRef<Path> , config : JournalConfig , start_seq : u64)->Result<Self , JournalError>{
+        Ok(Self { writer : JournalWriter::open(dir, INPUT_PREFIX, config)? , buf : Vec::with_capacity(64) , run_start : Some(start_seq) })
+    }
+
+    pub fn last_position(&self)->Option<u64>{
+        self.writer.last_seq()
+    }
+
+    pub fn record(&mut self , position : u64 , received_at : u64 , order : &ShmOrder)->Result<() , JournalError>{
+        self.buf.clear();
+        encode_input(self.run_start, received_at, order, &mut self.buf);
+        self.writer.append(position, &self.buf)?;
+        self.run_start = None;
+        self.writer.commit()
+    }
+
+    // nothing left to hand over , this only runs an interval sync that came due while the queue was quiet
+    pub fn commit(&mut self)->Result<() , JournalError>{
+        self.writer.commit()
+    }
+
+    pub fn sync(&mut self)->Result<() , JournalError>{
+        self.writer.sync()
+    }
+}
+
+// inputs from queue position `from` on
+pub fn read_inputs(dir : impl AsRef<Path> , from : u64)->Result<impl Iterator<Item = Result<JournaledInput , ReadError>> , JournalError>{
+    let reader = JournalReader::open(dir, INPUT_PREFIX, from)?;
+    Ok(reader.map(|record| {
+        let record = record?;
+        decode_input(record.seq, &record.payload).map_err(|error| ReadError::Decode { seq : record.seq , error })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::segment::FsyncPolicy;
+
+    #[test]
+    fn test_inputs_round_trip() {
+        let dir = std::env::temp_dir().join(format!("test_command_journal_{}" , std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let order = ShmOrder {
+            order_id : 7 , price : 101 , timestamp : 5 , client_id : 3 , shares_qty : 40 , symbol : 2 , side : 1 , order_type : 3 ,
+            time_in_force : 1 , post_only : 2 , session_state : 4 , stop_price : 99 , display_qty : 10 , _padding : [9 ; 5] ,
+            ..ShmOrder::default()
+        };
+        let config = JournalConfig { fsync : FsyncPolicy::Never , ..JournalConfig::default() };
+        let mut journal = CommandJournal::open(&dir, config, 0).unwrap();
+        journal.record(0, 1_000, &order).unwrap();
+        journal.record(1, 1_001, &ShmOrder::cancel(7, 2, 3, 6)).unwrap();
+        drop(journal);
+        // a restart carries on in the same journal
+        let mut journal = CommandJournal::open(&dir, config, 40).unwrap();
+        journal.record(2, 1_002, &order).unwrap();
+        journal.sync().unwrap();
+        assert_eq!(journal.last_position(), Some(2));
+
+        let inputs : Vec<JournaledInput> = read_inputs(&dir, 0).unwrap().map(Result::unwrap).collect();
+        assert_eq!(inputs.len(), 3);
+        assert_eq!((inputs[0].position , inputs[0].received_at) , (0 , 1_000));
+        let run_starts : Vec<Option<u64>> = inputs.iter().map(|input| input.run_start).collect();
+        assert_eq!(run_starts, vec![Some(0) , None , Some(40)]);
+        // everything but the padding comes back
+        assert_eq!(format!("{:?}" , inputs[0].order), format!("{:?}" , ShmOrder { _padding : [0 ; 5] , ..order }));
+        assert_eq!(inputs[1].order.msg_type, ShmOrder::CANCEL);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,63 @@
+// crc32 as used by zip and ethernet (ieee , reflected , polynomial 0xEDB88320)
+
+const TABLE : [u32 ; 256] = {
+    let mut table = [0u32 ; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Default for Crc32{
+    fn default()->Self{
+        Self(0xFFFF_FFFF)
+    }
+}
+
+impl Crc32{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    pub fn update(&mut self , bytes : &[u8]){
+        for &byte in bytes {
+            self.0 = TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn finish(self)->u32{
+        !self.0
+    }
+}
+
+pub fn crc32(bytes : &[u8])->u32{
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
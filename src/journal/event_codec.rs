@@ -0,0 +1,757 @@
+use crate::orderbook::order::Side;
+use crate::orderbook::session::{SessionAction, SessionState};
+use crate::orderbook::types::{
+    AuctionIndication, AuctionKind, AuctionResult, Bar, Bbo, BboChanged, Event, ExecType, ExecutionReport, Fill, Fills,
+    Liquidity, MatchResult, OrderAmended, OrderBookError, OrderCancelled, OrderIdAssigned, OrderRejected, PostOnlyOutcome,
+    PriceLevelChangedEvent, SelfTradePrevented, SequencedEvent, SessionChanged, StopTriggered, Trade, VolatilityInterruption,
+};
+
+// binary form of a SequencedEvent , little endian and fixed width , an Option is a 0/1 byte then the value
+// the tags below are on disk in every journal , never reuse or renumber one , add new ones at the end
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    // the record ended in the middle of a field
+    Truncated,
+    UnknownTag { what: &'static str, tag: u8 },
+    UnknownErrorCode(u16),
+    // bytes left over after the event , the record was written by something else
+    TrailingBytes(usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "record ends in the middle of a field"),
+            DecodeError::UnknownTag { what, tag } => write!(f, "unknown {} tag {}", what, tag),
+            DecodeError::UnknownErrorCode(code) => write!(f, "unknown reject reason code {}", code),
+            DecodeError::TrailingBytes(len) => write!(f, "{} bytes left after the event", len),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// the names the engine gives InvalidField , the error only holds a &'static str . only ever append
+const FIELD_NAMES: [&str; 6] = ["side", "order_type", "time_in_force", "post_only", "msg_type", "session_state"];
+const UNKNOWN_FIELD: u8 = u8::MAX;
+
+pub struct Encoder<'a> {
+    out: &'a mut Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(out: &'a mut Vec<u8>) -> Self {
+        Self { out }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u128(&mut self, value: u128) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.out.extend_from_slice(bytes);
+    }
+
+    pub fn option<T>(&mut self, value: Option<T>, mut put: impl FnMut(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                put(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn side(&mut self, side: Side) {
+        self.u8(match side {
+            Side::Bid => 0,
+            Side::Ask => 1,
+        });
+    }
+
+    fn session_state(&mut self, state: SessionState) {
+        self.u8(match state {
+            SessionState::PreOpen => 0,
+            SessionState::Continuous => 1,
+            SessionState::PreClose => 2,
+            SessionState::Halted => 3,
+            SessionState::Closed => 4,
+        });
+    }
+
+    fn session_action(&mut self, action: SessionAction) {
+        self.u8(match action {
+            SessionAction::NewOrder => 0,
+            SessionAction::Amend => 1,
+            SessionAction::Cancel => 2,
+            SessionAction::Match => 3,
+        });
+    }
+
+    fn fills(&mut self, fills: &Fills) {
+        self.u32(fills.fills.len() as u32);
+        for fill in &fills.fills {
+            self.u64(fill.price);
+            self.u32(fill.quantity);
+            self.u64(fill.taker_order_id);
+            self.u64(fill.maker_order_id);
+            self.option(fill.aggressor_side, Self::side);
+        }
+    }
+
+    fn order_cancelled(&mut self, cancelled: &OrderCancelled) {
+        self.u64(cancelled.order_id);
+        self.side(cancelled.side);
+        self.u64(cancelled.price);
+        self.u32(cancelled.cancelled_qty);
+    }
+
+    fn volatility_interruption(&mut self, interruption: VolatilityInterruption) {
+        self.u64(interruption.trigger_price);
+        self.u64(interruption.reference_price);
+        self.u64(interruption.low);
+        self.u64(interruption.high);
+        self.u64(interruption.ends_at);
+    }
+
+    fn auction_indication(&mut self, indication: AuctionIndication) {
+        self.u64(indication.price);
+        self.u64(indication.matched_qty);
+        self.u64(indication.imbalance_qty);
+        self.option(indication.imbalance_side, Self::side);
+    }
+
+    // the code first , then whatever the variant carries
+    fn error(&mut self, error: OrderBookError) {
+        self.u16(error.code());
+        match error {
+            OrderBookError::OrderNotFound(order_id)
+            | OrderBookError::DuplicateOrderId(order_id)
+            | OrderBookError::StopNotAmendable(order_id) => self.u64(order_id),
+            OrderBookError::ZeroQuantity | OrderBookError::ZeroPrice | OrderBookError::ZeroStopPrice => {}
+            OrderBookError::InvalidField { field, value } => {
+                self.u8(FIELD_NAMES.iter().position(|name| *name == field).map_or(UNKNOWN_FIELD, |index| index as u8));
+                self.u8(value);
+            }
+            OrderBookError::UnknownSymbol(symbol) => self.u32(symbol),
+            OrderBookError::ActionNotAllowed { state, action } => {
+                self.session_state(state);
+                self.session_action(action);
+            }
+            OrderBookError::IllegalTransition { from, to } => {
+                self.session_state(from);
+                self.session_state(to);
+            }
+            OrderBookError::PriceOutsideBand { price, low, high } => {
+                self.u64(price);
+                self.u64(low);
+                self.u64(high);
+            }
+            OrderBookError::OffTick { price, tick_size } => {
+                self.u64(price);
+                self.u64(tick_size);
+            }
+            OrderBookError::OddLot { qty, lot_size } => {
+                self.u32(qty);
+                self.u32(lot_size);
+            }
+            OrderBookError::QtyOutOfRange { qty, min_qty, max_qty } => {
+                self.u32(qty);
+                self.u32(min_qty);
+                self.u32(max_qty);
+            }
+            OrderBookError::OutsideTradingHours { open, close } => {
+                self.u32(open);
+                self.u32(close);
+            }
+        }
+    }
+
+    fn event(&mut self, event: &Event) {
+        match event {
+            Event::PriceLevelChangedEvent(level) => {
+                self.u8(1);
+                self.u32(level.symbol);
+                self.side(level.side);
+                self.u64(level.quantity);
+                self.u64(level.price);
+                self.u32(level.order_count);
+            }
+            Event::MatchResult(result) => {
+                self.u8(2);
+                self.u64(result.order_id);
+                self.fills(&result.fills);
+                self.u32(result.remaining_qty);
+                self.u32(result.cancelled_qty);
+                self.option(result.post_only, |encoder, outcome| match outcome {
+                    PostOnlyOutcome::Rested => encoder.u8(0),
+                    PostOnlyOutcome::Rejected => encoder.u8(1),
+                    PostOnlyOutcome::Repriced { original_price, new_price } => {
+                        encoder.u8(2);
+                        encoder.u64(original_price);
+                        encoder.u64(new_price);
+                    }
+                });
+                self.u32(result.self_trade.prevented_qty);
+                self.u32(result.self_trade.taker_cancelled_qty);
+                self.u32(result.self_trade.cancelled_makers.len() as u32);
+                for cancelled in &result.self_trade.cancelled_makers {
+                    self.order_cancelled(cancelled);
+                }
+                self.u32(result.self_trade.reduced_makers.len() as u32);
+                for reduced in &result.self_trade.reduced_makers {
+                    self.order_cancelled(reduced);
+                }
+                self.option(result.volatility_interruption, Self::volatility_interruption);
+            }
+            Event::StopTriggered(stop) => {
+                self.u8(3);
+                self.u64(stop.order_id);
+                self.side(stop.side);
+                self.u64(stop.stop_price);
+                self.u64(stop.last_trade_price);
+            }
+            Event::OrderAmended(amended) => {
+                self.u8(4);
+                self.u64(amended.order_id);
+                self.side(amended.side);
+                self.u64(amended.old_price);
+                self.u32(amended.old_qty);
+                self.u64(amended.new_price);
+                self.u32(amended.new_qty);
+                self.bool(amended.priority_kept);
+            }
+            Event::OrderCancelled(cancelled) => {
+                self.u8(5);
+                self.order_cancelled(cancelled);
+            }
+            Event::AuctionIndication(indication) => {
+                self.u8(6);
+                self.auction_indication(*indication);
+            }
+            Event::AuctionUncrossed(result) => {
+                self.u8(7);
+                self.u8(match result.kind {
+                    AuctionKind::Opening => 0,
+                    AuctionKind::Closing => 1,
+                    AuctionKind::Volatility => 2,
+                });
+                self.option(result.indication, Self::auction_indication);
+                self.fills(&result.fills);
+            }
+            Event::SessionChanged(changed) => {
+                self.u8(8);
+                self.u32(changed.symbol);
+                self.session_state(changed.from);
+                self.session_state(changed.to);
+            }
+            Event::VolatilityInterruption(interruption) => {
+                self.u8(9);
+                self.volatility_interruption(*interruption);
+            }
+            Event::OrderRejected(rejected) => {
+                self.u8(10);
+                self.u64(rejected.order_id);
+                self.u16(rejected.reason_code);
+                self.error(rejected.reason);
+            }
+            Event::OrderIdAssigned(assigned) => {
+                self.u8(11);
+                self.u32(assigned.client_id);
+                self.u64(assigned.client_order_id);
+                self.u64(assigned.order_id);
+            }
+            Event::ExecutionReport(report) => {
+                self.u8(12);
+                self.u64(report.order_id);
+                self.u32(report.client_id);
+                self.u32(report.symbol);
+                self.option(report.side, Self::side);
+                self.u8(match report.exec_type {
+                    ExecType::New => 0,
+                    ExecType::PartialFill => 1,
+                    ExecType::Fill => 2,
+                    ExecType::Cancelled => 3,
+                    ExecType::Replaced => 4,
+                    ExecType::Rejected => 5,
+                    ExecType::Expired => 6,
+                    ExecType::Restated => 7,
+                });
+                self.u32(report.order_qty);
+                self.u32(report.cum_qty);
+                self.u32(report.leaves_qty);
+                self.f64(report.avg_price);
+                self.u64(report.last_price);
+                self.u32(report.last_qty);
+                self.option(report.liquidity, |encoder, liquidity| {
+                    encoder.u8(match liquidity {
+                        Liquidity::Maker => 0,
+                        Liquidity::Taker => 1,
+                        Liquidity::Auction => 2,
+                    })
+                });
+                self.option(report.reject_reason, Self::error);
+            }
+            Event::BboChanged(changed) => {
+                self.u8(13);
+                self.u32(changed.symbol);
+                self.option(changed.bbo.bid_price, Self::u64);
+                self.u32(changed.bbo.bid_qty);
+                self.option(changed.bbo.ask_price, Self::u64);
+                self.u32(changed.bbo.ask_qty);
+            }
+            Event::Trade(trade) => {
+                self.u8(14);
+                self.u64(trade.trade_id);
+                self.u32(trade.symbol);
+                self.option(trade.aggressor_side, Self::side);
+                self.u64(trade.price);
+                self.u32(trade.quantity);
+                self.u64(trade.timestamp);
+            }
+            Event::BarClosed(bar) => {
+                self.u8(15);
+                self.u32(bar.symbol);
+                self.u64(bar.interval);
+                self.u64(bar.start);
+                self.u64(bar.open);
+                self.u64(bar.high);
+                self.u64(bar.low);
+                self.u64(bar.close);
+                self.u64(bar.volume);
+                self.u128(bar.turnover);
+                self.u64(bar.trade_count);
+            }
+        }
+    }
+}
+
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let (head, rest) = self.buf.split_first_chunk::<N>().ok_or(DecodeError::Truncated)?;
+        self.buf = rest;
+        Ok(*head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn u128(&mut self) -> Result<u128, DecodeError> {
+        Ok(u128::from_le_bytes(self.take()?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, DecodeError> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(DecodeError::UnknownTag { what: "bool", tag }),
+        }
+    }
+
+    // no more bytes may follow
+    pub fn finish(&self) -> Result<(), DecodeError> {
+        match self.buf.len() {
+            0 => Ok(()),
+            len => Err(DecodeError::TrailingBytes(len)),
+        }
+    }
+
+    pub fn option<T>(&mut self, mut get: impl FnMut(&mut Self) -> Result<T, DecodeError>) -> Result<Option<T>, DecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => get(self).map(Some),
+            tag => Err(DecodeError::UnknownTag { what: "option", tag }),
+        }
+    }
+
+    fn side(&mut self) -> Result<Side, DecodeError> {
+        match self.u8()? {
+            0 => Ok(Side::Bid),
+            1 => Ok(Side::Ask),
+            tag => Err(DecodeError::UnknownTag { what: "side", tag }),
+        }
+    }
+
+    fn session_state(&mut self) -> Result<SessionState, DecodeError> {
+        let tag = self.u8()?;
+        SessionState::from_u8(tag).ok_or(DecodeError::UnknownTag { what: "session state", tag })
+    }
+
+    fn session_action(&mut self) -> Result<SessionAction, DecodeError> {
+        match self.u8()? {
+            0 => Ok(SessionAction::NewOrder),
+            1 => Ok(SessionAction::Amend),
+            2 => Ok(SessionAction::Cancel),
+            3 => Ok(SessionAction::Match),
+            tag => Err(DecodeError::UnknownTag { what: "session action", tag }),
+        }
+    }
+
+    // a length read off the record , capped by what is left so a bad one cannot allocate much
+    fn len(&mut self, min_item_size: usize) -> Result<usize, DecodeError> {
+        let len = self.u32()? as usize;
+        if len.saturating_mul(min_item_size) > self.buf.len() {
+            return Err(DecodeError::Truncated);
+        }
+        Ok(len)
+    }
+
+    fn fills(&mut self) -> Result<Fills, DecodeError> {
+        let len = self.len(29)?;
+        let mut fills = Vec::with_capacity(len);
+        for _ in 0..len {
+            fills.push(Fill {
+                price: self.u64()?,
+                quantity: self.u32()?,
+                taker_order_id: self.u64()?,
+                maker_order_id: self.u64()?,
+                aggressor_side: self.option(Self::side)?,
+            });
+        }
+        Ok(Fills { fills })
+    }
+
+    fn order_cancelled(&mut self) -> Result<OrderCancelled, DecodeError> {
+        Ok(OrderCancelled { order_id: self.u64()?, side: self.side()?, price: self.u64()?, cancelled_qty: self.u32()? })
+    }
+
+    fn volatility_interruption(&mut self) -> Result<VolatilityInterruption, DecodeError> {
+        Ok(VolatilityInterruption {
+            trigger_price: self.u64()?,
+            reference_price: self.u64()?,
+            low: self.u64()?,
+            high: self.u64()?,
+            ends_at: self.u64()?,
+        })
+    }
+
+    fn auction_indication(&mut self) -> Result<AuctionIndication, DecodeError> {
+        Ok(AuctionIndication {
+            price: self.u64()?,
+            matched_qty: self.u64()?,
+            imbalance_qty: self.u64()?,
+            imbalance_side: self.option(Self::side)?,
+        })
+    }
+
+    fn error(&mut self) -> Result<OrderBookError, DecodeError> {
+        let error = match self.u16()? {
+            1 => OrderBookError::OrderNotFound(self.u64()?),
+            2 => OrderBookError::DuplicateOrderId(self.u64()?),
+            3 => OrderBookError::ZeroQuantity,
+            4 => OrderBookError::ZeroPrice,
+            5 => OrderBookError::ZeroStopPrice,
+            6 => {
+                let field = FIELD_NAMES.get(self.u8()? as usize).copied().unwrap_or("unknown");
+                OrderBookError::InvalidField { field, value: self.u8()? }
+            }
+            7 => OrderBookError::UnknownSymbol(self.u32()?),
+            8 => OrderBookError::ActionNotAllowed { state: self.session_state()?, action: self.session_action()? },
+            9 => OrderBookError::IllegalTransition { from: self.session_state()?, to: self.session_state()? },
+            10 => OrderBookError::PriceOutsideBand { price: self.u64()?, low: self.u64()?, high: self.u64()? },
+            11 => OrderBookError::OffTick { price: self.u64()?, tick_size: self.u64()? },
+            12 => OrderBookError::OddLot { qty: self.u32()?, lot_size: self.u32()? },
+            13 => OrderBookError::QtyOutOfRange { qty: self.u32()?, min_qty: self.u32()?, max_qty: self.u32()? },
+            14 => OrderBookError::OutsideTradingHours { open: self.u32()?, close: self.u32()? },
+            15 => OrderBookError::StopNotAmendable(self.u64()?),
+            code => return Err(DecodeError::UnknownErrorCode(code)),
+        };
+        Ok(error)
+    }
+
+    fn event(&mut self) -> Result<Event, DecodeError> {
+        let event = match self.u8()? {
+            1 => Event::PriceLevelChangedEvent(PriceLevelChangedEvent {
+                symbol: self.u32()?,
+                side: self.side()?,
+                quantity: self.u64()?,
+                price: self.u64()?,
+                order_count: self.u32()?,
+            }),
+            2 => {
+                let order_id = self.u64()?;
+                let fills = self.fills()?;
+                let remaining_qty = self.u32()?;
+                let cancelled_qty = self.u32()?;
+                let post_only = self.option(|decoder| match decoder.u8()? {
+                    0 => Ok(PostOnlyOutcome::Rested),
+                    1 => Ok(PostOnlyOutcome::Rejected),
+                    2 => Ok(PostOnlyOutcome::Repriced { original_price: decoder.u64()?, new_price: decoder.u64()? }),
+                    tag => Err(DecodeError::UnknownTag { what: "post only outcome", tag }),
+                })?;
+                let prevented_qty = self.u32()?;
+                let taker_cancelled_qty = self.u32()?;
+                let len = self.len(21)?;
+                let mut cancelled_makers = Vec::with_capacity(len);
+                for _ in 0..len {
+                    cancelled_makers.push(self.order_cancelled()?);
+                }
+                let len = self.len(21)?;
+                let mut reduced_makers = Vec::with_capacity(len);
+                for _ in 0..len {
+                    reduced_makers.push(self.order_cancelled()?);
+                }
+                Event::MatchResult(MatchResult {
+                    order_id,
+                    fills,
+                    remaining_qty,
+                    cancelled_qty,
+                    post_only,
+                    self_trade: SelfTradePrevented { prevented_qty, taker_cancelled_qty, cancelled_makers, reduced_makers },
+                    volatility_interruption: self.option(Self::volatility_interruption)?,
+                })
+            }
+            3 => Event::StopTriggered(StopTriggered {
+                order_id: self.u64()?,
+                side: self.side()?,
+                stop_price: self.u64()?,
+                last_trade_price: self.u64()?,
+            }),
+            4 => Event::OrderAmended(OrderAmended {
+                order_id: self.u64()?,
+                side: self.side()?,
+                old_price: self.u64()?,
+                old_qty: self.u32()?,
+                new_price: self.u64()?,
+                new_qty: self.u32()?,
+                priority_kept: self.bool()?,
+            }),
+            5 => Event::OrderCancelled(self.order_cancelled()?),
+            6 => Event::AuctionIndication(self.auction_indication()?),
+            7 => {
+                let kind = match self.u8()? {
+                    0 => AuctionKind::Opening,
+                    1 => AuctionKind::Closing,
+                    2 => AuctionKind::Volatility,
+                    tag => return Err(DecodeError::UnknownTag { what: "auction kind", tag }),
+                };
+                Event::AuctionUncrossed(AuctionResult {
+                    kind,
+                    indication: self.option(Self::auction_indication)?,
+                    fills: self.fills()?,
+                })
+            }
+            8 => Event::SessionChanged(SessionChanged {
+                symbol: self.u32()?,
+                from: self.session_state()?,
+                to: self.session_state()?,
+            }),
+            9 => Event::VolatilityInterruption(self.volatility_interruption()?),
+            10 => Event::OrderRejected(OrderRejected { order_id: self.u64()?, reason_code: self.u16()?, reason: self.error()? }),
+            11 => Event::OrderIdAssigned(OrderIdAssigned {
+                client_id: self.u32()?,
+                client_order_id: self.u64()?,
+                order_id: self.u64()?,
+            }),
+            12 => Event::ExecutionReport(ExecutionReport {
+                order_id: self.u64()?,
+                client_id: self.u32()?,
+                symbol: self.u32()?,
+                side: self.option(Self::side)?,
+                exec_type: match self.u8()? {
+                    0 => ExecType::New,
+                    1 => ExecType::PartialFill,
+                    2 => ExecType::Fill,
+                    3 => ExecType::Cancelled,
+                    4 => ExecType::Replaced,
+                    5 => ExecType::Rejected,
+                    6 => ExecType::Expired,
+                    7 => ExecType::Restated,
+                    tag => return Err(DecodeError::UnknownTag { what: "exec type", tag }),
+                },
+                order_qty: self.u32()?,
+                cum_qty: self.u32()?,
+                leaves_qty: self.u32()?,
+                avg_price: self.f64()?,
+                last_price: self.u64()?,
+                last_qty: self.u32()?,
+                liquidity: self.option(|decoder| match decoder.u8()? {
+                    0 => Ok(Liquidity::Maker),
+                    1 => Ok(Liquidity::Taker),
+                    2 => Ok(Liquidity::Auction),
+                    tag => Err(DecodeError::UnknownTag { what: "liquidity", tag }),
+                })?,
+                reject_reason: self.option(Self::error)?,
+            }),
+            13 => Event::BboChanged(BboChanged {
+                symbol: self.u32()?,
+                bbo: Bbo {
+                    bid_price: self.option(Self::u64)?,
+                    bid_qty: self.u32()?,
+                    ask_price: self.option(Self::u64)?,
+                    ask_qty: self.u32()?,
+                },
+            }),
+            14 => Event::Trade(Trade {
+                trade_id: self.u64()?,
+                symbol: self.u32()?,
+                aggressor_side: self.option(Self::side)?,
+                price: self.u64()?,
+                quantity: self.u32()?,
+                timestamp: self.u64()?,
+            }),
+            15 => Event::BarClosed(Bar {
+                symbol: self.u32()?,
+                interval: self.u64()?,
+                start: self.u64()?,
+                open: self.u64()?,
+                high: self.u64()?,
+                low: self.u64()?,
+                close: self.u64()?,
+                volume: self.u64()?,
+                turnover: self.u128()?,
+                trade_count: self.u64()?,
+            }),
+            tag => return Err(DecodeError::UnknownTag { what: "event", tag }),
+        };
+        Ok(event)
+    }
+}
+
+// where emitted_at sits in an encoded event
+pub const EMITTED_AT : std::ops::Range<usize> = 44..52;
+
+// the envelope then the event , `out` is appended to
+pub fn encode_event(event: &SequencedEvent, out: &mut Vec<u8>) {
+    let mut encoder = Encoder::new(out);
+    encoder.u64(event.engine_id as u64);
+    encoder.u64(event.seq);
+    encoder.u32(event.symbol);
+    encoder.u64(event.symbol_seq);
+    encoder.u64(event.input_position);
+    encoder.u64(event.received_at);
+    encoder.u64(event.emitted_at);
+    encoder.event(&event.event);
+}
+
+pub fn decode_event(buf: &[u8]) -> Result<SequencedEvent, DecodeError> {
+    let mut decoder = Decoder::new(buf);
+    let event = SequencedEvent {
+        engine_id: decoder.u64()? as usize,
+        seq: decoder.u64()?,
+        symbol: decoder.u32()?,
+        symbol_seq: decoder.u64()?,
+        input_position: decoder.u64()?,
+        received_at: decoder.u64()?,
+        emitted_at: decoder.u64()?,
+        event: decoder.event()?,
+    };
+    decoder.finish()?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequenced(seq: u64, event: Event) -> SequencedEvent {
+        SequencedEvent { engine_id: 2, seq, symbol: 7, symbol_seq: seq, input_position: 40 + seq, received_at: 11, emitted_at: 12, event }
+    }
+
+    #[test]
+    fn test_round_trip_every_event() {
+        let mut result = MatchResult::new(9, 50);
+        result.add_transaction(Fill { aggressor_side: Some(Side::Bid), ..Fill::new(100, 20, 9, 3) });
+        result.post_only = Some(PostOnlyOutcome::Repriced { original_price: 101, new_price: 99 });
+        result.self_trade.cancelled_makers.push(OrderCancelled { order_id: 4, side: Side::Ask, price: 100, cancelled_qty: 5 });
+        result.self_trade.reduced_makers.push(OrderCancelled { order_id: 5, side: Side::Ask, price: 100, cancelled_qty: 2 });
+        result.volatility_interruption = Some(VolatilityInterruption { trigger_price: 120, reference_price: 100, low: 90, high: 110, ends_at: 5 });
+        let report = ExecutionReport {
+            order_id: 9, client_id: 1, symbol: 7, side: None, exec_type: ExecType::Rejected, order_qty: 50, cum_qty: 20,
+            leaves_qty: 0, avg_price: 100.5, last_price: 0, last_qty: 0, liquidity: Some(Liquidity::Auction),
+            reject_reason: Some(OrderBookError::ActionNotAllowed { state: SessionState::Halted, action: SessionAction::Amend }),
+        };
+        let events = vec![
+            Event::PriceLevelChangedEvent(PriceLevelChangedEvent { symbol: 7, side: Side::Ask, quantity: 30, price: 101, order_count: 2 }),
+            Event::MatchResult(result),
+            Event::StopTriggered(StopTriggered { order_id: 5, side: Side::Ask, stop_price: 95, last_trade_price: 94 }),
+            Event::OrderAmended(OrderAmended { order_id: 6, side: Side::Bid, old_price: 1, old_qty: 2, new_price: 3, new_qty: 4, priority_kept: true }),
+            Event::OrderCancelled(OrderCancelled { order_id: 6, side: Side::Bid, price: 3, cancelled_qty: 4 }),
+            Event::AuctionIndication(AuctionIndication { price: 100, matched_qty: 10, imbalance_qty: 3, imbalance_side: None }),
+            Event::AuctionUncrossed(AuctionResult { kind: AuctionKind::Closing, indication: None, fills: Fills::default() }),
+            Event::SessionChanged(SessionChanged { symbol: 7, from: SessionState::PreOpen, to: SessionState::Continuous }),
+            Event::VolatilityInterruption(VolatilityInterruption { trigger_price: 1, reference_price: 2, low: 3, high: 4, ends_at: 5 }),
+            Event::OrderRejected(OrderRejected::new(8, OrderBookError::InvalidField { field: "post_only", value: 9 })),
+            Event::OrderIdAssigned(OrderIdAssigned { client_id: 1, client_order_id: 2, order_id: 3 }),
+            Event::ExecutionReport(report),
+            Event::BboChanged(BboChanged { symbol: 7, bbo: Bbo { bid_price: Some(99), bid_qty: 5, ask_price: None, ask_qty: 0 } }),
+            Event::Trade(Trade { trade_id: 1, symbol: 7, aggressor_side: Some(Side::Ask), price: 100, quantity: 3, timestamp: 77 }),
+            Event::BarClosed(Bar { symbol: 7, interval: 60, start: 0, open: 1, high: 4, low: 1, close: 2, volume: 9, turnover: 1 << 100, trade_count: 3 }),
+        ];
+        for (seq, event) in events.into_iter().enumerate() {
+            let event = sequenced(seq as u64 + 1, event);
+            let mut buf = Vec::new();
+            encode_event(&event, &mut buf);
+            let decoded = decode_event(&buf).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", event));
+        }
+    }
+
+    #[test]
+    fn test_bad_records() {
+        let mut buf = Vec::new();
+        encode_event(&sequenced(1, Event::OrderCancelled(OrderCancelled { order_id: 6, side: Side::Bid, price: 3, cancelled_qty: 4 })), &mut buf);
+        assert_eq!(decode_event(&buf[..buf.len() - 1]).unwrap_err(), DecodeError::Truncated);
+        let mut longer = buf.clone();
+        longer.push(0);
+        assert_eq!(decode_event(&longer).unwrap_err(), DecodeError::TrailingBytes(1));
+        assert_eq!(buf[EMITTED_AT], 12u64.to_le_bytes());
+        // the event tag sits right after the 52 byte envelope
+        buf[52] = 200;
+        assert_eq!(decode_event(&buf).unwrap_err(), DecodeError::UnknownTag { what: "event", tag: 200 });
+    }
+}
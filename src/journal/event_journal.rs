@@ -0,0 +1,108 @@
+use std::io;
+use std::path::Path;
+use crate::journal::event_codec::{decode_event, encode_event};
+use crate::journal::segment::{JournalConfig, JournalError, JournalReader, JournalWriter, ReadError};
+use crate::orderbook::types::SequencedEvent;
+use crate::publisher::sink::EventSink;
+
+// every event of one engine on disk , keyed by its engine wide seq
+pub const EVENT_PREFIX : &str = "events";
+
+// the journal as a publisher sink , every batch is appended then committed under the fsync policy
+// one journal per engine , seq only goes up within one engine and a restarted engine has to carry on from
+// last_seq . the journal holds every seq , once a batch
+// was lost every later one is refused with a Gap rather than written past the hole
+pub struct EventJournalSink{
+    name : String ,
+    writer : JournalWriter ,
+    buf : Vec<u8>
+}
+
+impl EventJournalSink{
+    pub fn open(dir : impl AsRef<Path> , config : JournalConfig)->Result<Self , JournalError>{
+        let name = format!("journal {}" , dir.as_ref().display());
+        Ok(Self { name , writer : JournalWriter::open(dir, EVENT_PREFIX, config)? , buf : Vec::with_capacity(256) })
+    }
+
+}
+
+impl EventSink for EventJournalSink{
+    fn name(&self)->&str{
+        &self.name
+    }
+
+    // a seq at or below the last one on disk is refused by the writer , the engine was not started after it
+    fn publish(&mut self , batch : &[SequencedEvent])->io::Result<()>{
+        for event in batch{
+            if let Some(last) = self.writer.last_seq() && event.seq > last + 1{
+                return Err(JournalError::Gap { last , seq : event.seq }.into());
+            }
+            self.buf.clear();
+            encode_event(event, &mut self.buf);
+            self.writer.append(event.seq, &self.buf)?;
+        }
+        self.writer.commit()?;
+        Ok(())
+    }
+
+    fn flush(&mut self)->io::Result<()>{
+        self.writer.sync()?;
+        Ok(())
+    }
+
+    fn last_seq(&self)->Option<u64>{
+        self.writer.last_seq()
+    }
+}
+
+// events from `from_seq` on , in the order they were sent
+pub fn read_events(dir : impl AsRef<Path> , from_seq : u64)->Result<impl Iterator<Item = Result<SequencedEvent , ReadError>> , JournalError>{
+    let reader = JournalReader::open(dir, EVENT_PREFIX, from_seq)?;
+    Ok(reader.map(|record| {
+        let record = record?;
+        decode_event(&record.payload).map_err(|error| ReadError::Decode { seq : record.seq , error })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::my_engine::{Engine, MyEngine};
+    use crate::journal::segment::FsyncPolicy;
+    use crate::orderbook::order::ShmOrder;
+
+    #[test]
+    fn test_engine_events_through_the_journal() {
+        let dir = std::env::temp_dir().join(format!("test_event_journal_{}" , std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (tx , rx) = crossbeam::channel::unbounded();
+        let mut engine = MyEngine::new(tx , 0);
+        engine.add_book(0);
+        engine.process_order(ShmOrder { order_id : 1 , side : 1 , shares_qty : 10 , price : 100 , ..ShmOrder::default() });
+        engine.process_order(ShmOrder { order_id : 2 , side : 0 , shares_qty : 4 , price : 100 , ..ShmOrder::default() });
+        let sent : Vec<SequencedEvent> = rx.try_iter().collect();
+
+        let config = JournalConfig { segment_bytes : 512 , fsync : FsyncPolicy::EveryRecord };
+        let mut sink = EventJournalSink::open(&dir, config).unwrap();
+        sink.publish(&sent[..3]).unwrap();
+        drop(sink);
+        // reopened it carries on after what it holds , going back is an error rather than a silent skip
+        let mut sink = EventJournalSink::open(&dir, config).unwrap();
+        assert_eq!(sink.last_seq(), Some(3));
+        let err = sink.publish(&sent[2..]).unwrap_err();
+        assert!(err.to_string().contains("seq 3 written after 3"));
+        sink.publish(&sent[3..]).unwrap();
+        sink.flush().unwrap();
+
+        let read : Vec<SequencedEvent> = read_events(&dir, 0).unwrap().map(Result::unwrap).collect();
+        assert_eq!(format!("{:?}" , read), format!("{:?}" , sent));
+        // a lost event is a hole nothing is written past
+        engine.process_order(ShmOrder { order_id : 3 , side : 0 , shares_qty : 4 , price : 100 , ..ShmOrder::default() });
+        let more : Vec<SequencedEvent> = rx.try_iter().collect();
+        assert!(sink.publish(&more[1..]).is_err());
+        assert_eq!(sink.last_seq(), Some(sent.len() as u64));
+        let tail : Vec<u64> = read_events(&dir, 5).unwrap().map(|event| event.unwrap().seq).collect();
+        assert_eq!(tail, (5..=sent.len() as u64).collect::<Vec<_>>());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
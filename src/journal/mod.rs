@@ -0,0 +1,6 @@
+pub mod crc;
+pub mod segment;
+pub mod event_codec;
+pub mod event_journal;
+pub mod command_journal;
+pub mod replay;
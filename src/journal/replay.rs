@@ -0,0 +1,179 @@
+use std::path::Path;
+use crate::engine::my_engine::MyEngine;
+use crate::journal::command_journal::read_inputs;
+use crate::journal::event_codec::{encode_event, EMITTED_AT};
+use crate::journal::segment::ReadError;
+use crate::orderbook::types::SequencedEvent;
+
+// feeds every journaled input through an engine in the order the original runs took them
+// `setup` builds the engine the way the original process did at start , same engine id , books and instruments .
+// every restart the journal recorded starts over with a fresh one seeded with the seq that run began at ,
+// the books the process lost when it went down are lost here too . events go out on the channel the engines
+// were given . returns how many inputs were replayed
+pub fn replay(setup : impl Fn()->MyEngine , dir : impl AsRef<Path>)->Result<u64 , ReadError>{
+    let mut engine = None;
+    let mut count = 0;
+    for input in read_inputs(dir, 0)?{
+        let input = input?;
+        if input.run_start.is_some() || engine.is_none(){
+            let mut fresh = setup();
+            fresh.seq = input.run_start.unwrap_or(0);
+            fresh.replaying = true;
+            engine = Some(fresh);
+        }
+        if let Some(engine) = &mut engine{
+            engine.replay_input(&input);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+// the first place two event streams part , None for either side that already ended
+#[derive(Debug)]
+pub struct Divergence{
+    // how many events matched before it
+    pub index : u64 ,
+    pub original : Option<SequencedEvent> ,
+    pub replayed : Option<SequencedEvent>
+}
+
+// the bytes an event is compared on , emitted_at reads the clock as the event leaves so it is left out
+fn comparable(event : &SequencedEvent , buf : &mut Vec<u8>){
+    buf.clear();
+    encode_event(event, buf);
+    buf[EMITTED_AT].fill(0);
+}
+
+// compares the original run's events with the replay's , byte for byte in the journal encoding
+pub fn first_difference(original : impl IntoIterator<Item = SequencedEvent> , replayed : impl IntoIterator<Item = SequencedEvent>)->Option<Divergence>{
+    let mut original = original.into_iter();
+    let mut replayed = replayed.into_iter();
+    let (mut left , mut right) = (Vec::new() , Vec::new());
+    let mut index = 0;
+    loop{
+        match (original.next() , replayed.next()){
+            (None , None) => return None,
+            (Some(a) , Some(b)) => {
+                comparable(&a, &mut left);
+                comparable(&b, &mut right);
+                if left != right{
+                    return Some(Divergence { index , original : Some(a) , replayed : Some(b) });
+                }
+            }
+            (a , b) => return Some(Divergence { index , original : a , replayed : b }),
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::market_stats::ONE_SECOND;
+    use crate::engine::my_engine::Engine;
+    use crate::engine::order_ids::OrderIds;
+    use crate::journal::command_journal::CommandJournal;
+    use crate::journal::segment::JournalConfig;
+    use crate::orderbook::order::ShmOrder;
+
+    fn order(order_id : u64 , side : u8 , order_type : u8 , qty : u32 , price : u64 , timestamp : u64)->ShmOrder{
+        ShmOrder { order_id , client_id : order_id as u32 % 3 , side , order_type , shares_qty : qty , price , timestamp , ..ShmOrder::default() }
+    }
+
+    fn engine(tx : &crossbeam::channel::Sender<SequencedEvent>)->MyEngine{
+        let mut engine = MyEngine::new(tx.clone() , 1);
+        engine.add_book(0);
+        engine.add_book(1);
+        engine
+    }
+
+    #[test]
+    fn test_replay_reproduces_the_run() {
+        let dir = std::env::temp_dir().join(format!("test_replay_{}" , std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let inputs = [
+            order(1, 1, 0, 10, 101, 0),
+            order(2, 1, 0, 10, 102, 10),
+            ShmOrder { symbol : 1 , ..order(3, 0, 0, 5, 50, 20) },
+            order(4, 0, 1, 15, 0, ONE_SECOND),
+            ShmOrder { msg_type : ShmOrder::AMEND , ..order(2, 1, 0, 2, 103, ONE_SECOND + 1) },
+            ShmOrder::cancel(9, 0, 0, ONE_SECOND + 2),
+            order(5, 7, 0, 1, 1, ONE_SECOND + 3),
+            ShmOrder::session(1, 3, 2 * ONE_SECOND),
+            order(6, 0, 0, 3, 103, 3 * ONE_SECOND),
+        ];
+        let (live_tx , live_rx) = crossbeam::channel::unbounded();
+        let mut live = engine(&live_tx);
+        live.input_journal = Some(CommandJournal::open(&dir, JournalConfig::default(), 0).unwrap());
+        for (position , input) in inputs.iter().enumerate(){
+            live.accept_input(*input, position as u64).unwrap();
+        }
+        live.input_journal.take().unwrap().sync().unwrap();
+        let original : Vec<SequencedEvent> = live_rx.try_iter().collect();
+        assert!(original.len() > 20);
+
+        let (tx , rx) = crossbeam::channel::unbounded();
+        assert_eq!(replay(|| engine(&tx), &dir).unwrap(), inputs.len() as u64);
+        let replayed : Vec<SequencedEvent> = rx.try_iter().collect();
+        assert!(first_difference(original.iter().map(copy), replayed).is_none());
+
+        // an engine set up differently gives itself away
+        let scoped = || {
+            let mut engine = engine(&tx);
+            engine.order_ids = OrderIds::scoped();
+            engine
+        };
+        replay(scoped, &dir).unwrap();
+        let divergence = first_difference(original.iter().map(copy), rx.try_iter()).unwrap();
+        assert!(divergence.index < 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_replay_starts_over_at_a_restart() {
+        let dir = std::env::temp_dir().join(format!("test_replay_restart_{}" , std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let first_run = [
+            order(1, 1, 0, 10, 101, 0),
+            order(2, 1, 0, 10, 102, 10),
+            order(3, 0, 0, 4, 101, 20),
+        ];
+        let second_run = [
+            // the first run's orders went down with it , this amend is rejected
+            ShmOrder { msg_type : ShmOrder::AMEND , ..order(2, 1, 0, 2, 103, 30) },
+            order(4, 1, 0, 10, 101, 40),
+            order(5, 0, 1, 15, 0, 50),
+        ];
+        let (live_tx , live_rx) = crossbeam::channel::unbounded();
+        let mut live = engine(&live_tx);
+        live.input_journal = Some(CommandJournal::open(&dir, JournalConfig::default(), 0).unwrap());
+        for (position , input) in first_run.iter().enumerate(){
+            live.accept_input(*input, position as u64).unwrap();
+        }
+        let last_seq = live.seq;
+        drop(live);
+
+        // the restarted process starts with empty books and carries the seq on , like main does off the event journal
+        let mut live = engine(&live_tx);
+        live.seq = last_seq;
+        live.input_journal = Some(CommandJournal::open(&dir, JournalConfig::default(), last_seq).unwrap());
+        for (position , input) in second_run.iter().enumerate(){
+            live.accept_input(*input, (first_run.len() + position) as u64).unwrap();
+        }
+        live.input_journal.take().unwrap().sync().unwrap();
+        let original : Vec<SequencedEvent> = live_rx.try_iter().collect();
+        assert!(original.iter().any(|event| matches!(event.event , crate::orderbook::types::Event::OrderRejected(_))));
+
+        let (tx , rx) = crossbeam::channel::unbounded();
+        assert_eq!(replay(|| engine(&tx), &dir).unwrap(), 6);
+        assert!(first_difference(original.iter().map(copy), rx.try_iter()).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    fn copy(event : &SequencedEvent)->SequencedEvent{
+        let mut buf = Vec::new();
+        encode_event(event, &mut buf);
+        crate::journal::event_codec::decode_event(&buf).unwrap()
+    }
+}
@@ -0,0 +1,567 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::journal::crc::Crc32;
+use crate::journal::event_codec::DecodeError;
+
+// append only log of numbered records split over segment files in one directory
+//
+//   <prefix>-<first seq , 20 digits>.log    one per segment , named after its first record
+//   segment = magic , then records
+//   record  = payload len u32 , crc32 u32 of seq and payload , seq u64 , payload    all little endian
+//
+// sequence numbers only go up . a record a crash cut short or left unchecksummed at the very end of the newest
+// segment is dropped when the journal is opened for writing again , anywhere else a bad record is reported as corrupt
+
+pub const SEGMENT_MAGIC : [u8 ; 8] = *b"OBJRNL01";
+const RECORD_HEADER : usize = 16;
+// nothing the engine writes comes close , a bigger length is garbage
+pub const MAX_PAYLOAD : u32 = 16 << 20;
+
+// when written records are forced to disk , a segment is always synced when it is rolled or closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy{
+    // left to the os
+    Never ,
+    EveryRecord ,
+    // on the first commit at least this long after the last sync
+    Interval(Duration)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JournalConfig{
+    // a segment is rolled before a record would take it past this size
+    pub segment_bytes : u64 ,
+    pub fsync : FsyncPolicy
+}
+
+impl Default for JournalConfig{
+    fn default()->Self{
+        Self { segment_bytes : 256 << 20 , fsync : FsyncPolicy::Interval(Duration::from_millis(100)) }
+    }
+}
+
+#[derive(Debug)]
+pub enum JournalError{
+    Io(io::Error),
+    // `offset` is where the bad record starts in the segment
+    Corrupt { segment : PathBuf , offset : u64 , reason : &'static str },
+    // records must carry increasing sequence numbers
+    OutOfOrder { last : u64 , seq : u64 },
+    // an earlier write failed and may have left part of a record , reopening the journal recovers its tail
+    Failed ,
+    // a journal that has to hold every seq was handed one past a hole , what should have filled it was lost
+    Gap { last : u64 , seq : u64 },
+    PayloadTooLarge(usize)
+}
+
+impl std::fmt::Display for JournalError{
+    fn fmt(&self , f : &mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self{
+            JournalError::Io(e) => write!(f , "journal io error {}" , e),
+            JournalError::Corrupt { segment , offset , reason } => write!(f , "{} corrupt at {} , {}" , segment.display() , offset , reason),
+            JournalError::OutOfOrder { last , seq } => write!(f , "seq {} written after {}" , seq , last),
+            JournalError::Failed => write!(f , "an earlier write failed , the journal has to be reopened"),
+            JournalError::Gap { last , seq } => write!(f , "seq {} would leave a gap after {}" , seq , last),
+            JournalError::PayloadTooLarge(len) => write!(f , "record of {} bytes is over the limit" , len),
+        }
+    }
+}
+
+impl std::error::Error for JournalError{}
+
+impl From<io::Error> for JournalError{
+    fn from(e : io::Error)->Self{
+        JournalError::Io(e)
+    }
+}
+
+impl From<JournalError> for io::Error{
+    fn from(e : JournalError)->Self{
+        match e{
+            JournalError::Io(e) => e,
+            e => io::Error::other(e),
+        }
+    }
+}
+
+// reading a journal back , either the records themselves or what they decode to is bad
+#[derive(Debug)]
+pub enum ReadError{
+    Journal(JournalError),
+    Decode { seq : u64 , error : DecodeError }
+}
+
+impl std::fmt::Display for ReadError{
+    fn fmt(&self , f : &mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self{
+            ReadError::Journal(e) => write!(f , "{}" , e),
+            ReadError::Decode { seq , error } => write!(f , "record {} does not decode , {}" , seq , error),
+        }
+    }
+}
+
+impl std::error::Error for ReadError{}
+
+impl From<JournalError> for ReadError{
+    fn from(e : JournalError)->Self{
+        ReadError::Journal(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record{
+    pub seq : u64 ,
+    pub payload : Vec<u8>
+}
+
+fn segment_name(prefix : &str , first_seq : u64)->String{
+    format!("{}-{:020}.log" , prefix , first_seq)
+}
+
+// every segment of the journal , oldest first
+pub fn segments(dir : &Path , prefix : &str)->Result<Vec<(u64 , PathBuf)> , JournalError>{
+    let mut segments = Vec::new();
+    let entries = match fs::read_dir(dir){
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(segments),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries{
+        let path = entry?.path();
+        let first_seq = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(prefix)?.strip_prefix('-')?.strip_suffix(".log")?.parse().ok());
+        if let Some(first_seq) = first_seq{
+            segments.push((first_seq , path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+enum ReadOutcome{
+    Record(Record),
+    // clean end of the segment
+    End,
+    // the segment stops inside a record
+    Truncated,
+    // `end` is where the bad record claims to stop , at or past the end of the file it is the last thing written
+    BadChecksum { end : u64 },
+    BadLength { end : u64 }
+}
+
+// one segment read front to back
+struct SegmentReader{
+    path : PathBuf ,
+    reader : BufReader<File> ,
+    offset : u64 ,
+    // file size when it was opened
+    len : u64
+}
+
+impl SegmentReader{
+    // None for a file too short to hold the magic
+    fn open(path : PathBuf)->Result<Option<Self> , JournalError>{
+        let file = File::open(&path)?;
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8 ; 8];
+        if read_full(&mut reader, &mut magic)? < magic.len(){
+            return Ok(None);
+        }
+        if magic != SEGMENT_MAGIC{
+            return Err(JournalError::Corrupt { segment : path , offset : 0 , reason : "not a journal segment" });
+        }
+        Ok(Some(Self { path , reader , offset : magic.len() as u64 , len }))
+    }
+
+    fn next(&mut self)->Result<ReadOutcome , JournalError>{
+        let mut header = [0u8 ; RECORD_HEADER];
+        match read_full(&mut self.reader, &mut header)?{
+            0 => return Ok(ReadOutcome::End),
+            RECORD_HEADER => {}
+            _ => return Ok(ReadOutcome::Truncated),
+        }
+        let len = u32::from_le_bytes(header[0..4].try_into().expect("4 bytes"));
+        let crc = u32::from_le_bytes(header[4..8].try_into().expect("4 bytes"));
+        let seq = u64::from_le_bytes(header[8..16].try_into().expect("8 bytes"));
+        let end = self.offset + RECORD_HEADER as u64 + len as u64;
+        if len > MAX_PAYLOAD{
+            return Ok(ReadOutcome::BadLength { end });
+        }
+        let mut payload = vec![0u8 ; len as usize];
+        if read_full(&mut self.reader, &mut payload)? < payload.len(){
+            return Ok(ReadOutcome::Truncated);
+        }
+        let mut check = Crc32::new();
+        check.update(&header[8..16]);
+        check.update(&payload);
+        if check.finish() != crc{
+            return Ok(ReadOutcome::BadChecksum { end });
+        }
+        self.offset += (RECORD_HEADER + payload.len()) as u64;
+        Ok(ReadOutcome::Record(Record { seq , payload }))
+    }
+
+    // what an unfinished write leaves at the end of the file : a short record , or a bad one that reaches the end .
+    // in the newest segment that is the end of the journal , the writer cuts it off when it reopens
+    fn is_torn_tail(&self , outcome : &ReadOutcome)->bool{
+        match outcome{
+            ReadOutcome::Truncated => true,
+            ReadOutcome::BadChecksum { end } | ReadOutcome::BadLength { end } => *end >= self.len,
+            ReadOutcome::Record(_) | ReadOutcome::End => false
+        }
+    }
+
+    fn corrupt(&self , reason : &'static str)->JournalError{
+        JournalError::Corrupt { segment : self.path.clone() , offset : self.offset , reason }
+    }
+}
+
+// like read_exact but says how much it got before the end of the file
+fn read_full(reader : &mut impl Read , buf : &mut [u8])->io::Result<usize>{
+    let mut filled = 0;
+    while filled < buf.len(){
+        match reader.read(&mut buf[filled..]){
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// walks the journal from a sequence number on , across segments
+// stops after the first error , a torn record at the very end of the journal just ends it
+pub struct JournalReader{
+    segments : std::vec::IntoIter<(u64 , PathBuf)> ,
+    current : Option<SegmentReader> ,
+    // the newest segment is being read , a torn record at its end is an unfinished write
+    on_last : bool ,
+    from_seq : u64 ,
+    done : bool
+}
+
+impl JournalReader{
+    pub fn open(dir : impl AsRef<Path> , prefix : &str , from_seq : u64)->Result<Self , JournalError>{
+        let mut segments = segments(dir.as_ref(), prefix)?;
+        // the segment holding from_seq is the last one starting at or before it
+        let start = segments.iter().rposition(|(first_seq , _)| *first_seq <= from_seq).unwrap_or(0);
+        segments.drain(..start);
+        Ok(Self { segments : segments.into_iter() , current : None , on_last : false , from_seq , done : false })
+    }
+
+    fn next_record(&mut self)->Result<Option<Record> , JournalError>{
+        loop{
+            let Some(current) = &mut self.current else {
+                let Some((_ , path)) = self.segments.next() else { return Ok(None) };
+                self.on_last = self.segments.len() == 0;
+                self.current = SegmentReader::open(path)?;
+                continue;
+            };
+            let outcome = current.next()?;
+            if self.on_last && current.is_torn_tail(&outcome){
+                return Ok(None);
+            }
+            let reason = match outcome{
+                ReadOutcome::Record(record) if record.seq < self.from_seq => continue,
+                ReadOutcome::Record(record) => return Ok(Some(record)),
+                ReadOutcome::End => {
+                    self.current = None;
+                    continue;
+                }
+                ReadOutcome::Truncated => "segment ends inside a record",
+                ReadOutcome::BadChecksum { .. } => "checksum mismatch",
+                ReadOutcome::BadLength { .. } => "record length over the limit",
+            };
+            return Err(current.corrupt(reason));
+        }
+    }
+}
+
+impl Iterator for JournalReader{
+    type Item = Result<Record , JournalError>;
+
+    fn next(&mut self)->Option<Self::Item>{
+        if self.done{
+            return None;
+        }
+        let next = self.next_record().transpose();
+        if !matches!(next , Some(Ok(_))){
+            self.done = true;
+        }
+        next
+    }
+}
+
+// appends records , one writer per journal
+pub struct JournalWriter{
+    dir : PathBuf ,
+    prefix : String ,
+    pub config : JournalConfig ,
+    // the open segment and how many bytes it holds , None until the first record after open or a roll
+    file : Option<BufWriter<File>> ,
+    segment_len : u64 ,
+    last_seq : Option<u64> ,
+    // set by a failed write , nothing is appended after what it may have left behind
+    failed : bool ,
+    unsynced : bool ,
+    last_sync : Instant ,
+    buf : Vec<u8>
+}
+
+impl JournalWriter{
+    // carries on after whatever the journal already holds , an unfinished record at its end is cut off .
+    // a bad record with more after it is not something a crash leaves , that is Corrupt
+    pub fn open(dir : impl AsRef<Path> , prefix : &str , config : JournalConfig)->Result<Self , JournalError>{
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut writer = Self {
+            dir , prefix : prefix.to_string() , config , file : None , segment_len : 0 , last_seq : None , failed : false ,
+            unsynced : false , last_sync : Instant::now() , buf : Vec::new()
+        };
+        writer.recover()?;
+        Ok(writer)
+    }
+
+    pub fn last_seq(&self)->Option<u64>{
+        self.last_seq
+    }
+
+    fn recover(&mut self)->Result<() , JournalError>{
+        let mut segments = segments(&self.dir, &self.prefix)?;
+        while let Some((_ , path)) = segments.pop(){
+            let mut valid_len = 0;
+            let mut last_seq = None;
+            if let Some(mut reader) = SegmentReader::open(path.clone())?{
+                valid_len = reader.offset;
+                loop{
+                    match reader.next()?{
+                        ReadOutcome::Record(record) => {
+                            last_seq = Some(record.seq);
+                            valid_len = reader.offset;
+                        }
+                        ReadOutcome::End => break,
+                        // the tail of a write the process did not finish
+                        outcome if reader.is_torn_tail(&outcome) => break,
+                        ReadOutcome::Truncated => break,
+                        ReadOutcome::BadChecksum { .. } => return Err(reader.corrupt("checksum mismatch before the end of the newest segment")),
+                        ReadOutcome::BadLength { .. } => return Err(reader.corrupt("record length over the limit before the end of the newest segment")),
+                    }
+                }
+            }
+            match last_seq{
+                Some(last_seq) => {
+                    let file = OpenOptions::new().write(true).open(&path)?;
+                    file.set_len(valid_len)?;
+                    file.sync_all()?;
+                    let mut file = BufWriter::new(file);
+                    io::Seek::seek(&mut file, io::SeekFrom::End(0))?;
+                    self.file = Some(file);
+                    self.segment_len = valid_len;
+                    self.last_seq = Some(last_seq);
+                    return Ok(());
+                }
+                // nothing in it made it to disk , the one before holds the end of the journal
+                None => fs::remove_file(&path)?,
+            }
+        }
+        Ok(())
+    }
+
+    // new segment named after `first_seq` , the current one is synced and closed first
+    fn roll(&mut self , first_seq : u64)->Result<() , JournalError>{
+        self.close_segment()?;
+        let path = self.dir.join(segment_name(&self.prefix, first_seq));
+        let mut file = BufWriter::new(OpenOptions::new().write(true).create(true).truncate(true).open(path)?);
+        file.write_all(&SEGMENT_MAGIC)?;
+        self.file = Some(file);
+        self.segment_len = SEGMENT_MAGIC.len() as u64;
+        Ok(())
+    }
+
+    fn close_segment(&mut self)->Result<() , JournalError>{
+        if let Some(mut file) = self.file.take(){
+            file.flush()?;
+            if self.config.fsync != FsyncPolicy::Never{
+                file.get_ref().sync_data()?;
+            }
+        }
+        self.unsynced = false;
+        Ok(())
+    }
+
+    pub fn append(&mut self , seq : u64 , payload : &[u8])->Result<() , JournalError>{
+        if self.failed{
+            return Err(JournalError::Failed);
+        }
+        if let Some(last) = self.last_seq && seq <= last{
+            return Err(JournalError::OutOfOrder { last , seq });
+        }
+        if payload.len() > MAX_PAYLOAD as usize{
+            return Err(JournalError::PayloadTooLarge(payload.len()));
+        }
+        let written = self.write_record(seq, payload);
+        self.failed = written.is_err();
+        written
+    }
+
+    fn write_record(&mut self , seq : u64 , payload : &[u8])->Result<() , JournalError>{
+        let record_len = (RECORD_HEADER + payload.len()) as u64;
+        let full = self.segment_len + record_len > self.config.segment_bytes && self.segment_len > SEGMENT_MAGIC.len() as u64;
+        if self.file.is_none() || full{
+            self.roll(seq)?;
+        }
+        let mut crc = Crc32::new();
+        crc.update(&seq.to_le_bytes());
+        crc.update(payload);
+        self.buf.clear();
+        self.buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.buf.extend_from_slice(&crc.finish().to_le_bytes());
+        self.buf.extend_from_slice(&seq.to_le_bytes());
+        self.buf.extend_from_slice(payload);
+        let file = self.file.as_mut().expect("rolled above");
+        file.write_all(&self.buf)?;
+        self.segment_len += record_len;
+        self.last_seq = Some(seq);
+        self.unsynced = true;
+        if self.config.fsync == FsyncPolicy::EveryRecord{
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    // end of a batch , hands what was appended to the os and syncs if the policy says so
+    pub fn commit(&mut self)->Result<() , JournalError>{
+        if self.failed{
+            return Err(JournalError::Failed);
+        }
+        if let Some(file) = &mut self.file && let Err(e) = file.flush(){
+            self.failed = true;
+            return Err(e.into());
+        }
+        if let FsyncPolicy::Interval(interval) = self.config.fsync && self.last_sync.elapsed() >= interval{
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    pub fn sync(&mut self)->Result<() , JournalError>{
+        if self.failed{
+            return Err(JournalError::Failed);
+        }
+        if let Some(file) = &mut self.file && self.unsynced
+            && let Err(e) = file.flush().and_then(|()| file.get_ref().sync_data()){
+                self.failed = true;
+                return Err(e.into());
+        }
+        self.unsynced = false;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+impl Drop for JournalWriter{
+    fn drop(&mut self){
+        if let Err(e) = self.close_segment(){
+            eprintln!("[JOURNAL] failed to close segment: {}" , e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name : &str)->PathBuf{
+        let dir = std::env::temp_dir().join(format!("{}_{}" , name , std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn seqs(reader : JournalReader)->Vec<u64>{
+        reader.map(|record| record.unwrap().seq).collect()
+    }
+
+    #[test]
+    fn test_segments_roll_and_read_from_any_seq() {
+        let dir = temp_dir("test_journal_roll");
+        let config = JournalConfig { segment_bytes : 64 , fsync : FsyncPolicy::Never };
+        let mut writer = JournalWriter::open(&dir, "events", config).unwrap();
+        for seq in 1..=6{
+            writer.append(seq, &[seq as u8 ; 20]).unwrap();
+        }
+        assert!(matches!(writer.append(6, b"again") , Err(JournalError::OutOfOrder { last : 6 , seq : 6 })));
+        writer.commit().unwrap();
+        // magic plus one 36 byte record per segment
+        assert_eq!(segments(&dir, "events").unwrap().len(), 6);
+
+        assert_eq!(seqs(JournalReader::open(&dir, "events", 0).unwrap()), vec![1 , 2 , 3 , 4 , 5 , 6]);
+        assert_eq!(seqs(JournalReader::open(&dir, "events", 4).unwrap()), vec![4 , 5 , 6]);
+        let record = JournalReader::open(&dir, "events", 5).unwrap().next().unwrap().unwrap();
+        assert_eq!(record.payload, vec![5 ; 20]);
+        assert!(JournalReader::open(&dir, "events", 9).unwrap().next().is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_torn_tail_is_cut_and_corruption_reported() {
+        let dir = temp_dir("test_journal_torn");
+        let mut writer = JournalWriter::open(&dir, "events", JournalConfig::default()).unwrap();
+        writer.append(1, b"first").unwrap();
+        writer.append(2, b"second").unwrap();
+        drop(writer);
+        let (_ , path) = segments(&dir, "events").unwrap().pop().unwrap();
+        // a crash half way through the second record
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 3).unwrap();
+        assert_eq!(seqs(JournalReader::open(&dir, "events", 0).unwrap()), vec![1]);
+
+        let mut writer = JournalWriter::open(&dir, "events", JournalConfig::default()).unwrap();
+        assert_eq!(writer.last_seq(), Some(1));
+        writer.append(2, b"second again").unwrap();
+        drop(writer);
+        let records : Vec<Record> = JournalReader::open(&dir, "events", 0).unwrap().map(Result::unwrap).collect();
+        assert_eq!(records[1].payload, b"second again");
+
+        // a last record that is all there but does not check out was never finished , it goes too
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+        // a reader that gets there before the writer reopens sees the same end
+        assert_eq!(seqs(JournalReader::open(&dir, "events", 0).unwrap()), vec![1]);
+        let mut writer = JournalWriter::open(&dir, "events", JournalConfig::default()).unwrap();
+        assert_eq!(writer.last_seq(), Some(1));
+        writer.append(2, b"second").unwrap();
+        drop(writer);
+
+        // so does a last record whose length was never written properly
+        let mut bytes = fs::read(&path).unwrap();
+        let second = SEGMENT_MAGIC.len() + RECORD_HEADER + b"first".len();
+        bytes[second..second + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(seqs(JournalReader::open(&dir, "events", 0).unwrap()), vec![1]);
+        let writer = JournalWriter::open(&dir, "events", JournalConfig::default()).unwrap();
+        assert_eq!(writer.last_seq(), Some(1));
+        drop(writer);
+
+        // flip a payload byte of the first record , with a good one after it that is damage not a torn write
+        let mut writer = JournalWriter::open(&dir, "events", JournalConfig::default()).unwrap();
+        writer.append(2, b"second").unwrap();
+        drop(writer);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[SEGMENT_MAGIC.len() + RECORD_HEADER] ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+        let mut reader = JournalReader::open(&dir, "events", 0).unwrap();
+        assert!(matches!(reader.next() , Some(Err(JournalError::Corrupt { offset : 8 , reason : "checksum mismatch" , .. }))));
+        assert!(reader.next().is_none());
+        // the writer refuses it rather than cutting the good record off
+        assert!(matches!(JournalWriter::open(&dir, "events", JournalConfig::default()) , Err(JournalError::Corrupt { offset : 8 , .. })));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod orderbook;
 pub mod engine ;
 pub mod publisher;
-pub mod shm ;
\ No newline at end of file
+pub mod shm ;
+pub mod journal;
@@ -1,18 +1,48 @@
 use std::thread::JoinHandle;
-use rust_orderbook_2::orderbook::{ types::Event};
+use rust_orderbook_2::orderbook::{ types::SequencedEvent};
 use rust_orderbook_2::engine::my_engine::{Engine, MyEngine};
-use rust_orderbook_2::publisher::event_publisher::EventPublisher;
-use core_affinity;
+use rust_orderbook_2::publisher::event_publisher::{EventPublisher, Overflow, SinkConfig};
+use rust_orderbook_2::publisher::sink;
+use rust_orderbook_2::journal::command_journal::CommandJournal;
+use rust_orderbook_2::journal::segment::JournalConfig;
+use rust_orderbook_2::orderbook::instrument::InstrumentRegistry;
 
 fn main(){
-    let (event_sender , event_rec) = crossbeam::channel::bounded::<Event>(10000000);
+    env_logger::init();
+    let (event_sender , event_rec) = crossbeam::channel::bounded::<SequencedEvent>(10000000);
     let sender_clone = event_sender.clone();
+    // optional instrument config as the first argument , without it the engine trades symbol 0 unrestricted 
+    let registry = std::env::args().nth(1).map(|path| InstrumentRegistry::load(&path).expect("Failed to load instruments"));
+    // where events go , e.g. ORDERBOOK_SINKS=journal:/var/lib/orderbook/journal,udp:239.0.0.1:5000 
+    // with none set every event is dropped after draining 
+    let mut sinks = Vec::new();
+    for spec in std::env::var("ORDERBOOK_SINKS").unwrap_or_default().split(',').filter(|spec| !spec.is_empty()) {
+        let sink = sink::from_spec(spec).expect("Failed to open sink");
+        // the journal is the record of the day , it may slow the publisher down but never misses a batch .
+        // a batch it failed to write is a hole it refuses to write past , so it stops at the first one 
+        let config = if spec.starts_with("journal:") {
+            SinkConfig { overflow : Overflow::Block , give_up_after : Some(1) , ..SinkConfig::default() }
+        } else {
+            SinkConfig::default()
+        };
+        sinks.push((sink , config));
+    }
+    // a journal from an earlier run already holds seqs , this run's events carry on after them 
+    let last_seq = sinks.iter().filter_map(|(sink , _)| sink.last_seq()).max().unwrap_or(0);
     let mut  running_engines : Vec<JoinHandle<()>> = Vec::new();
     let first_join_handle = std::thread::spawn(move ||{
         let _cores = core_affinity::get_core_ids().expect("Failed to get core IDs");
         core_affinity::set_for_current(core_affinity::CoreId { id:  1 });
         let mut engine = MyEngine::new(sender_clone , 0);
-        engine.add_book(0);
+        engine.seq = last_seq;
+        match &registry {
+            Some(registry) => engine.load_instruments(registry),
+            None => engine.add_book(0)
+        }
+        // every input journaled before it is processed , `replay` runs it again 
+        if let Ok(dir) = std::env::var("ORDERBOOK_INPUT_JOURNAL") {
+            engine.input_journal = Some(CommandJournal::open(&dir, JournalConfig::default(), engine.seq).expect("Failed to open input journal"));
+        }
         engine.run_engine();
     });
     running_engines.push(first_join_handle);
@@ -23,6 +53,9 @@ fn main(){
     let publisher_handle  = std::thread::spawn(move||{
         core_affinity::set_for_current(core_affinity::CoreId { id: 5 });
         let mut my_publisher = EventPublisher::new(event_rec);
+        for (sink , config) in sinks {
+            my_publisher.add_sink(sink, config);
+        }
         my_publisher.start_publisher();
     });
 
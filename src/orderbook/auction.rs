@@ -0,0 +1,107 @@
+use crate::orderbook::book::BookSide;
+use crate::orderbook::order::Side;
+use crate::orderbook::types::AuctionIndication;
+
+// the single price a call auction uncrosses at
+// maximises executed volume , then minimises the imbalance left over , then sits closest to the
+// reference price , and finally takes the lowest price so the answer is always deterministic
+// returns None when nothing crosses
+pub fn equilibrium(bids : &BookSide , asks : &BookSide , reference_price : Option<u64>)->Option<AuctionIndication>{
+    let mut candidates : Vec<u64> = bids.levels.keys().chain(asks.levels.keys()).copied().collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let mut best : Option<AuctionIndication> = None;
+    for price in candidates{
+        let buy_qty = volume_at_or_better(bids, |bid| bid >= price);
+        let sell_qty = volume_at_or_better(asks, |ask| ask <= price);
+        let matched_qty = buy_qty.min(sell_qty);
+        if matched_qty == 0{
+            continue;
+        }
+        let imbalance_side = match buy_qty.cmp(&sell_qty){
+            std::cmp::Ordering::Greater => Some(Side::Bid),
+            std::cmp::Ordering::Less => Some(Side::Ask),
+            std::cmp::Ordering::Equal => None,
+        };
+        let candidate = AuctionIndication{
+            price ,
+            matched_qty ,
+            imbalance_qty : buy_qty.abs_diff(sell_qty) ,
+            imbalance_side
+        };
+        if best.as_ref().is_none_or(|current| better(&candidate, current, reference_price)){
+            best = Some(candidate);
+        }
+    }
+    best
+}
+
+// displayed plus hidden volume over every level `accepts` lets through
+fn volume_at_or_better(side : &BookSide , accepts : impl Fn(u64)->bool)->u64{
+    side.levels.values()
+        .filter(|level| accepts(level.price))
+        .map(|level| level.get_total_volume() as u64 + level.hidden_vol as u64)
+        .sum()
+}
+
+// candidates are visited from the lowest price up , so an exact tie keeps the lower price
+fn better(candidate : &AuctionIndication , current : &AuctionIndication , reference_price : Option<u64>)->bool{
+    if candidate.matched_qty != current.matched_qty{
+        return candidate.matched_qty > current.matched_qty;
+    }
+    if candidate.imbalance_qty != current.imbalance_qty{
+        return candidate.imbalance_qty < current.imbalance_qty;
+    }
+    match reference_price{
+        Some(reference) => candidate.price.abs_diff(reference) < current.price.abs_diff(reference),
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::order::Order;
+    use crate::orderbook::order_manager::OrderManager;
+
+    fn side_with(side : Side , levels : &[(u64 , u32)] , manager : &mut OrderManager)->BookSide{
+        let mut book_side = BookSide::new(side);
+        for &(price , qty) in levels{
+            book_side.insert(Order::new(manager.all_orders.len() as u64 + 1, side, qty, price, 0, 0), manager);
+        }
+        book_side
+    }
+
+    #[test]
+    fn test_equilibrium_maximises_volume() {
+        let mut manager = OrderManager::new();
+        let bids = side_with(Side::Bid, &[(102, 10), (101, 20), (100, 30)], &mut manager);
+        let asks = side_with(Side::Ask, &[(99, 10), (100, 15), (101, 20)], &mut manager);
+        // at 100 buyers have 60 and sellers 25 , at 101 buyers 30 and sellers 45
+        let indication = equilibrium(&bids, &asks, None).unwrap();
+        assert_eq!(indication.price, 101);
+        assert_eq!(indication.matched_qty, 30);
+        assert_eq!(indication.imbalance_qty, 15);
+        assert_eq!(indication.imbalance_side, Some(Side::Ask));
+    }
+
+    #[test]
+    fn test_equilibrium_reference_price_tie_break() {
+        let mut manager = OrderManager::new();
+        let bids = side_with(Side::Bid, &[(105, 10)], &mut manager);
+        let asks = side_with(Side::Ask, &[(100, 10)], &mut manager);
+        // every price from 100 to 105 matches 10 with no imbalance
+        assert_eq!(equilibrium(&bids, &asks, None).unwrap().price, 100);
+        assert_eq!(equilibrium(&bids, &asks, Some(104)).unwrap().price, 105);
+        assert_eq!(equilibrium(&bids, &asks, Some(101)).unwrap().price, 100);
+    }
+
+    #[test]
+    fn test_no_cross_no_equilibrium() {
+        let mut manager = OrderManager::new();
+        let bids = side_with(Side::Bid, &[(99, 10)], &mut manager);
+        let asks = side_with(Side::Ask, &[(100, 10)], &mut manager);
+        assert!(equilibrium(&bids, &asks, None).is_none());
+    }
+}
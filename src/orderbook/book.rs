@@ -1,32 +1,74 @@
-use std::collections::BTreeMap ; 
+use std::collections::{BTreeMap, BTreeSet} ; 
 use crate::orderbook::{order::Side, price_level::PriceLevel , order::Order};
+use crate::orderbook::types::PriceLevelChangedEvent;
 use crate::orderbook::order_manager::OrderManager;
 
 #[derive(Debug)]
 pub struct BookSide{
     side : Side ,
     pub levels : BTreeMap<u64 , PriceLevel>,
+    // prices changed since the last L2 updates were taken 
+    pub touched : BTreeSet<u64>,
 }
 
 impl BookSide{
     pub fn new(side : Side)->Self{
         Self{
             side , 
-            levels : BTreeMap::new()
+            levels : BTreeMap::new(),
+            touched : BTreeSet::new()
         }
     }
 
-    pub fn get_best_price(&mut self)->Option<u64>{
+    pub fn get_best_price(&self)->Option<u64>{
         match self.side{
-            Side::Bid => self.levels.keys().rev().next().cloned(),
+            Side::Bid => self.levels.keys().next_back().cloned(),
             Side::Ask => self.levels.keys().next().cloned(),
         }
     }
 
+    // price and displayed volume at the top of this side 
+    pub fn best_level(&self)->Option<(u64 , u32)>{
+        let level = match self.side{
+            Side::Bid => self.levels.values().next_back(),
+            Side::Ask => self.levels.values().next(),
+        };
+        level.map(|level| (level.price , level.get_total_volume()))
+    }
+
+    // how much of `wanted` could be filled against this side at levels `accepts` lets through , walking the makers
+    // in priority order . `offers` says what a maker gives , None for one the walk cannot get past
+    pub fn fillable_qty(&self , manager : &OrderManager , accepts : impl Fn(u64)->bool , offers : impl Fn(&Order)->Option<u32> , wanted : u32)->u32{
+        let levels : Box<dyn Iterator<Item = &PriceLevel>> = match self.side{
+            Side::Bid => Box::new(self.levels.values().rev()),
+            Side::Ask => Box::new(self.levels.values()),
+        };
+        let mut available = 0u32;
+        for level in levels{
+            if !accepts(level.price){
+                break;
+            }
+            let mut current = level.head;
+            while let Some(order_key) = current{
+                if available >= wanted{
+                    return wanted;
+                }
+                let maker = &manager.all_orders[order_key];
+                match offers(maker){
+                    Some(qty) => available = available.saturating_add(qty),
+                    None => return available.min(wanted)
+                }
+                current = maker.next;
+            }
+        }
+        available.min(wanted)
+    }
+
     pub fn insert(&mut self , order:Order , manager : &mut OrderManager){
         let price = {
             order.price
         };
+        self.touched.insert(price);
         let level =self.levels.entry(price)
         .or_insert_with(||PriceLevel::new(price)
         );
@@ -34,14 +76,29 @@ impl BookSide{
     }    
 
     pub fn remove_level_if_empty(&mut self, price: u64) {
-        if let Some(level) = self.levels.get(&price) {
-            if level.head.is_none() && level.tail.is_none(){
+        if let Some(level) = self.levels.get(&price)
+            && level.head.is_none() && level.tail.is_none(){
                 self.levels.remove(&price);
-            }
         }
     }
     pub fn delete_order(&mut self , price : u64 , manager : &mut OrderManager , order_id : u64){
-        self.levels.get_mut(&price).as_deref_mut().unwrap().delete_order(order_id, manager);
+        self.touched.insert(price);
+        self.levels.get_mut(&price).unwrap().delete_order(order_id, manager);
+        self.remove_level_if_empty(price);
     }
 
-}
\ No newline at end of file
+    // marks a level changed by code that works on `levels` directly 
+    pub fn touch(&mut self , price : u64){
+        self.touched.insert(price);
+    }
+
+    // one update per price touched since the last call , a level that is gone reports zero 
+    pub fn take_level_updates(&mut self , symbol : u32)->Vec<PriceLevelChangedEvent>{
+        let touched = std::mem::take(&mut self.touched);
+        touched.into_iter().map(|price| {
+            let (quantity , order_count) = self.levels.get(&price)
+                .map_or((0 , 0) , |level| (level.get_total_volume() as u64 , level.order_count));
+            PriceLevelChangedEvent{ symbol , side : self.side , price , quantity , order_count }
+        }).collect()
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+// reference data for one tradable symbol , the book checks every incoming order against it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instrument{
+    pub symbol : u32 ,
+    pub name : String ,
+    // prices must be a multiple of this
+    pub tick_size : u64 ,
+    // quantities must be a multiple of this
+    pub lot_size : u32 ,
+    pub min_qty : u32 ,
+    pub max_qty : u32 ,
+    // new orders and amends are only taken inside these , None trades around the clock
+    pub trading_hours : Option<TradingHours>
+}
+
+// opening and closing time as seconds after midnight utc , a session that closes before it opens runs over midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradingHours{
+    pub open : u32 ,
+    pub close : u32
+}
+
+impl TradingHours{
+    const SECONDS_PER_DAY : u64 = 86_400;
+
+    // `timestamp` is nanoseconds since the unix epoch like the engine's receive time
+    pub fn contains(&self , timestamp : u64)->bool{
+        let second_of_day = (timestamp / 1_000_000_000 % Self::SECONDS_PER_DAY) as u32;
+        if self.open <= self.close{
+            second_of_day >= self.open && second_of_day < self.close
+        }
+        else {
+            second_of_day >= self.open || second_of_day < self.close
+        }
+    }
+
+    // "09:30-16:00"
+    fn parse(value : &str)->Option<Self>{
+        let (open , close) = value.split_once('-')?;
+        Some(Self { open : parse_time(open)? , close : parse_time(close)? })
+    }
+}
+
+fn parse_time(value : &str)->Option<u32>{
+    let (hours , minutes) = value.trim().split_once(':')?;
+    let (hours , minutes) : (u32 , u32) = (hours.parse().ok()? , minutes.parse().ok()?);
+    if hours > 23 || minutes > 59{
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60)
+}
+
+impl Instrument{
+    // no restrictions beyond a positive quantity , what a book gets when nothing was configured
+    pub fn new(symbol : u32 , name : &str)->Self{
+        Self {
+            symbol ,
+            name : name.to_string() ,
+            tick_size : 1 ,
+            lot_size : 1 ,
+            min_qty : 1 ,
+            max_qty : u32::MAX ,
+            trading_hours : None
+        }
+    }
+
+    pub fn is_on_tick(&self , price : u64)->bool{
+        price.is_multiple_of(self.tick_size)
+    }
+
+    pub fn is_round_lot(&self , qty : u32)->bool{
+        qty.is_multiple_of(self.lot_size)
+    }
+
+    pub fn qty_in_range(&self , qty : u32)->bool{
+        qty >= self.min_qty && qty <= self.max_qty
+    }
+}
+
+#[derive(Debug)]
+pub enum RegistryError{
+    Io(std::io::Error),
+    // line numbers start at 1
+    Parse { line : usize , message : String }
+}
+
+// every instrument the engine knows about , keyed by symbol
+#[derive(Debug, Default)]
+pub struct InstrumentRegistry{
+    pub instruments : HashMap<u32 , Instrument>
+}
+
+impl InstrumentRegistry{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    pub fn insert(&mut self , instrument : Instrument)->Option<Instrument>{
+        self.instruments.insert(instrument.symbol, instrument)
+    }
+
+    pub fn get(&self , symbol : u32)->Option<&Instrument>{
+        self.instruments.get(&symbol)
+    }
+
+    pub fn len(&self)->usize{
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.instruments.is_empty()
+    }
+
+    pub fn load(path : impl AsRef<Path>)->Result<Self , RegistryError>{
+        let contents = std::fs::read_to_string(path).map_err(RegistryError::Io)?;
+        Self::parse(&contents)
+    }
+
+    // one section per instrument , keys left out keep the defaults of `Instrument::new`
+    //
+    //   # comments and blank lines are ignored
+    //   [1]
+    //   name = BTCUSD
+    //   tick_size = 5
+    //   lot_size = 10
+    //   min_qty = 10
+    //   max_qty = 100000
+    //   trading_hours = 09:30-16:00
+    pub fn parse(contents : &str)->Result<Self , RegistryError>{
+        let mut registry = Self::new();
+        let mut current : Option<Instrument> = None;
+        for (index , raw_line) in contents.lines().enumerate(){
+            let line_no = index + 1;
+            let error = |message : &str| RegistryError::Parse { line : line_no , message : message.to_string() };
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#'){
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')){
+                let symbol : u32 = section.trim().parse().map_err(|_| error("symbol must be a number"))?;
+                if let Some(done) = current.take(){
+                    registry.add_checked(done, line_no)?;
+                }
+                current = Some(Instrument::new(symbol, &symbol.to_string()));
+                continue;
+            }
+            let instrument = current.as_mut().ok_or_else(|| error("key before the first [symbol] section"))?;
+            let (key , value) = line.split_once('=').ok_or_else(|| error("expected key = value"))?;
+            let (key , value) = (key.trim() , value.trim());
+            match key{
+                "name" => instrument.name = value.to_string(),
+                "tick_size" => instrument.tick_size = value.parse().map_err(|_| error("tick_size must be a number"))?,
+                "lot_size" => instrument.lot_size = value.parse().map_err(|_| error("lot_size must be a number"))?,
+                "min_qty" => instrument.min_qty = value.parse().map_err(|_| error("min_qty must be a number"))?,
+                "max_qty" => instrument.max_qty = value.parse().map_err(|_| error("max_qty must be a number"))?,
+                "trading_hours" => instrument.trading_hours = Some(TradingHours::parse(value).ok_or_else(|| error("trading_hours must look like 09:30-16:00"))?),
+                _ => return Err(error(&format!("unknown key {}" , key)))
+            }
+        }
+        if let Some(done) = current.take(){
+            registry.add_checked(done, contents.lines().count())?;
+        }
+        Ok(registry)
+    }
+
+    fn add_checked(&mut self , instrument : Instrument , line : usize)->Result<() , RegistryError>{
+        let error = |message : String| Err(RegistryError::Parse { line , message });
+        if instrument.tick_size == 0 || instrument.lot_size == 0{
+            return error(format!("symbol {} needs a non zero tick_size and lot_size" , instrument.symbol));
+        }
+        if instrument.min_qty == 0 || instrument.min_qty > instrument.max_qty{
+            return error(format!("symbol {} needs 0 < min_qty <= max_qty" , instrument.symbol));
+        }
+        if self.instruments.contains_key(&instrument.symbol){
+            return error(format!("symbol {} is defined twice" , instrument.symbol));
+        }
+        self.insert(instrument);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_registry() {
+        let registry = InstrumentRegistry::parse("
+            # two instruments
+            [1]
+            name = BTCUSD
+            tick_size = 5
+            lot_size = 10
+            min_qty = 10
+            max_qty = 1000
+            trading_hours = 09:30-16:00
+
+            [2]
+            name = ETHUSD
+        ").unwrap();
+        assert_eq!(registry.len(), 2);
+        let btc = registry.get(1).unwrap();
+        assert_eq!(btc.name, "BTCUSD");
+        assert_eq!(btc.tick_size, 5);
+        assert_eq!(btc.trading_hours, Some(TradingHours { open: 34_200, close: 57_600 }));
+        assert!(btc.is_on_tick(105) && !btc.is_on_tick(103));
+        assert!(btc.is_round_lot(20) && !btc.is_round_lot(25));
+        assert!(!btc.qty_in_range(1010));
+        // defaults for anything left out
+        assert_eq!(registry.get(2).unwrap().lot_size, 1);
+    }
+
+    #[test]
+    fn test_parse_errors_carry_the_line() {
+        assert!(matches!(InstrumentRegistry::parse("name = x"), Err(RegistryError::Parse { line: 1, .. })));
+        assert!(matches!(InstrumentRegistry::parse("[1]\ntick_size = abc"), Err(RegistryError::Parse { line: 2, .. })));
+        assert!(matches!(InstrumentRegistry::parse("[1]\n[1]"), Err(RegistryError::Parse { .. })));
+        assert!(matches!(InstrumentRegistry::parse("[1]\ntick_size = 0"), Err(RegistryError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_trading_hours_over_midnight() {
+        let hours = TradingHours::parse("22:00-02:00").unwrap();
+        let at = |h : u64| h * 3600 * 1_000_000_000;
+        assert!(hours.contains(at(23)));
+        assert!(hours.contains(at(1)));
+        assert!(!hours.contains(at(12)));
+    }
+}
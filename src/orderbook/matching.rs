@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+
+// decides how an incoming quantity is shared between the orders resting at one price level
+// every policy fills min(incoming , level total) so matching always makes progress
+pub trait MatchingPolicy : Debug + Send {
+    // `resting` is the displayed quantity of each order in time priority , the result lines up with it
+    fn allocate(&self , incoming_qty : u32 , resting : &[u32])->Vec<u32>;
+
+    // a policy that only ever fills from the front of the queue does not need to see the whole level
+    fn needs_full_level(&self)->bool{
+        true
+    }
+}
+
+// price time priority , the oldest order is filled completely before the next one gets anything
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fifo;
+
+impl MatchingPolicy for Fifo{
+    fn allocate(&self , incoming_qty : u32 , resting : &[u32])->Vec<u32>{
+        let mut left = incoming_qty;
+        resting.iter().map(|&qty| {
+            let allocated = qty.min(left);
+            left -= allocated;
+            allocated
+        }).collect()
+    }
+
+    fn needs_full_level(&self)->bool{
+        false
+    }
+}
+
+// every order gets a share proportional to its size , rounded down
+// shares below `min_allocation` get nothing and whatever is left over from rounding goes out in time priority
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProRata{
+    pub min_allocation : u32
+}
+
+impl MatchingPolicy for ProRata{
+    fn allocate(&self , incoming_qty : u32 , resting : &[u32])->Vec<u32>{
+        let total : u64 = resting.iter().map(|&qty| qty as u64).sum();
+        let to_fill = (incoming_qty as u64).min(total);
+        if to_fill == 0{
+            return vec![0 ; resting.len()];
+        }
+        let mut allocations : Vec<u32> = resting.iter().map(|&qty| {
+            let share = (to_fill * qty as u64 / total) as u32;
+            if share < self.min_allocation { 0 } else { share }
+        }).collect();
+
+        let mut left = to_fill as u32 - allocations.iter().sum::<u32>();
+        for (allocated , &qty) in allocations.iter_mut().zip(resting){
+            if left == 0{
+                break;
+            }
+            let extra = (qty - *allocated).min(left);
+            *allocated += extra;
+            left -= extra;
+        }
+        allocations
+    }
+}
+
+// the order at the head of the level is filled first , the rest is shared pro rata
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TopOrderProRata{
+    pub min_allocation : u32
+}
+
+impl MatchingPolicy for TopOrderProRata{
+    fn allocate(&self , incoming_qty : u32 , resting : &[u32])->Vec<u32>{
+        let Some((&top , rest)) = resting.split_first() else {
+            return Vec::new();
+        };
+        let top_allocation = top.min(incoming_qty);
+        let mut allocations = vec![top_allocation];
+        allocations.extend(ProRata{ min_allocation : self.min_allocation }.allocate(incoming_qty - top_allocation, rest));
+        allocations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fifo_fills_in_time_priority() {
+        assert_eq!(Fifo.allocate(70, &[50, 30, 20]), vec![50, 20, 0]);
+        assert_eq!(Fifo.allocate(200, &[50, 30]), vec![50, 30]);
+    }
+
+    #[test]
+    fn test_pro_rata_rounds_down_and_spreads_leftover_in_time_priority() {
+        let policy = ProRata { min_allocation: 0 };
+        // 10 * 50/100 = 5 , 10 * 30/100 = 3 , 10 * 20/100 = 2
+        assert_eq!(policy.allocate(10, &[50, 30, 20]), vec![5, 3, 2]);
+        // 7 -> 3.5 , 2.1 , 1.4 rounded down to 3 , 2 , 1 with 1 left for the oldest
+        assert_eq!(policy.allocate(7, &[50, 30, 20]), vec![4, 2, 1]);
+        assert_eq!(policy.allocate(500, &[50, 30, 20]), vec![50, 30, 20]);
+    }
+
+    #[test]
+    fn test_pro_rata_minimum_allocation() {
+        let policy = ProRata { min_allocation: 2 };
+        // the 20 lot would get 1 which is under the minimum , it goes to the oldest order instead
+        assert_eq!(policy.allocate(7, &[50, 30, 20]), vec![5, 2, 0]);
+    }
+
+    #[test]
+    fn test_top_order_then_pro_rata() {
+        let policy = TopOrderProRata { min_allocation: 0 };
+        assert_eq!(policy.allocate(30, &[10, 40, 40]), vec![10, 10, 10]);
+        assert_eq!(policy.allocate(5, &[10, 40, 40]), vec![5, 0, 0]);
+        assert!(policy.allocate(5, &[]).is_empty());
+    }
+}
@@ -5,4 +5,12 @@ pub mod order;
 pub mod order_manager;
 pub mod order_book;
 pub mod price_level;
-pub mod tests;
\ No newline at end of file
+pub mod stop_book;
+pub mod matching;
+pub mod auction;
+pub mod session;
+pub mod price_band;
+pub mod instrument;
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
\ No newline at end of file
@@ -8,39 +8,150 @@ pub  enum Side{
 }
 #[derive(Debug , Copy , Clone)]
 pub struct Order{
-   // pub order_type : Type,
+    pub order_type : Type,
+    pub time_in_force : TimeInForce,
+    pub post_only : PostOnly,
     pub order_id : OrderId , 
     pub side : Side , 
+    // for an iceberg this is only the displayed peak 
     pub shares_qty : u32 , 
+    // iceberg reserve not shown on the book , refills the peak once it is consumed 
+    pub hidden_qty : u32 ,
+    // iceberg display size , 0 for a normal order 
+    pub peak_qty : u32 ,
     pub price : u64 ,
+    // trigger price for stop and stop limit orders , 0 for everything else 
+    pub stop_price : u64 ,
     pub timestamp : u64 , 
     pub next : Option<OrderKey>,
     pub prev : Option<OrderKey>,
     pub symbol: u32,
+    // owner of the order , 0 when unknown 
+    pub client_id : u32,
 }
 
 impl Order{
     pub fn new(order_id : u64 , side : Side , shares_qty : u32 , price : u64 , timestamp :u64 , symbol : u32)->Self{
         Self{
+            order_type : Type::Limit,
+            time_in_force : TimeInForce::Gtc,
+            post_only : PostOnly::Off,
             order_id ,
             side ,
             shares_qty,
+            hidden_qty : 0 ,
+            peak_qty : 0 ,
             price ,
+            stop_price : 0 ,
             timestamp,
             next : None,
             prev : None , 
-            symbol
+            symbol,
+            client_id : 0
+        }
+    }
+
+    // market orders carry no price , whatever is not filled gets cancelled instead of resting
+    pub fn market(order_id : u64 , side : Side , shares_qty : u32 , timestamp : u64 , symbol : u32)->Self{
+        Self{
+            order_type : Type::Market,
+            time_in_force : TimeInForce::Ioc,
+            ..Self::new(order_id, side, shares_qty, 0, timestamp, symbol)
+        }
+    }
+
+    // limit order showing at most `peak_qty` of its `shares_qty` at a time
+    pub fn iceberg(order_id : u64 , side : Side , shares_qty : u32 , peak_qty : u32 , price : u64 , timestamp : u64 , symbol : u32)->Self{
+        let mut order = Self{
+            peak_qty ,
+            ..Self::new(order_id, side, shares_qty, price, timestamp, symbol)
+        };
+        order.split_reserve();
+        order
+    }
+
+    pub fn is_iceberg(&self)->bool{
+        self.peak_qty > 0
+    }
+
+    // visible plus hidden quantity 
+    pub fn total_qty(&self)->u32{
+        self.shares_qty.saturating_add(self.hidden_qty)
+    }
+
+    // folds the reserve back into the visible quantity 
+    pub fn merge_reserve(&mut self){
+        self.shares_qty = self.total_qty();
+        self.hidden_qty = 0;
+    }
+
+    // shows at most one peak and moves the rest into the reserve 
+    pub fn split_reserve(&mut self){
+        if self.is_iceberg(){
+            let total = self.total_qty();
+            self.shares_qty = total.min(self.peak_qty);
+            self.hidden_qty = total - self.shares_qty;
+        }
+    }
+
+    // cuts the total quantity down to `new_qty` , reserve first so the displayed peak stays as long as possible
+    // returns how much came out of (hidden , visible)
+    pub fn reduce_to(&mut self , new_qty : u32)->(u32 , u32){
+        let cut = self.total_qty().saturating_sub(new_qty);
+        let from_hidden = cut.min(self.hidden_qty);
+        self.hidden_qty -= from_hidden;
+        self.shares_qty = self.shares_qty.saturating_sub(cut - from_hidden);
+        (from_hidden , cut - from_hidden)
+    }
+
+    // becomes a market order once the last trade reaches `stop_price`
+    pub fn stop(order_id : u64 , side : Side , shares_qty : u32 , stop_price : u64 , timestamp : u64 , symbol : u32)->Self{
+        Self{
+            order_type : Type::Stop,
+            stop_price ,
+            ..Self::market(order_id, side, shares_qty, timestamp, symbol)
+        }
+    }
+
+    // becomes a limit order at `price` once the last trade reaches `stop_price`
+    pub fn stop_limit(order_id : u64 , side : Side , shares_qty : u32 , price : u64 , stop_price : u64 , timestamp : u64 , symbol : u32)->Self{
+        Self{
+            order_type : Type::StopLimit,
+            stop_price ,
+            ..Self::new(order_id, side, shares_qty, price, timestamp, symbol)
         }
     }
 }
 
-#[derive(Debug , Copy , Clone)]
+#[derive(Debug , Copy , Clone , PartialEq , Eq)]
 pub enum Type {
     Market ,
-    Limit 
+    Limit ,
+    Stop ,
+    StopLimit
 }
+
+#[derive(Debug , Copy , Clone , PartialEq , Eq)]
+pub enum TimeInForce {
+    // rests until filled or cancelled 
+    Gtc ,
+    // fills what it can now , the rest is cancelled 
+    Ioc ,
+    // fills completely now or not at all 
+    Fok
+}
+
+// maker only orders , what to do when the order would take liquidity 
+#[derive(Debug , Copy , Clone , PartialEq , Eq)]
+pub enum PostOnly {
+    Off ,
+    Reject ,
+    // reprice one tick behind the opposite best price 
+    Slide
+}
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ShmOrder{
     pub order_id: u64,
     pub price: u64,
@@ -52,22 +163,37 @@ pub struct ShmOrder{
     pub symbol: u32,
     pub side: u8,   // 0=buy, 1=sell
     pub status: u8, // 0=pending, 1=filled, 2=rejected
+    pub order_type: u8, // 0=limit, 1=market, 2=stop, 3=stop-limit
+    pub time_in_force: u8, // 0=gtc, 1=ioc, 2=fok
+    pub post_only: u8, // 0=off, 1=reject, 2=slide
+    pub msg_type: u8, // 0=new, 1=cancel, 2=amend, 3=session
+    pub session_state: u8, // session messages: 0=pre-open, 1=continuous, 2=pre-close, 3=halted, 4=closed
+    pub _padding: [u8; 5], // keeps stop_price 8-byte aligned
+    // Fields appended after the original 48 byte layout , the queue magic was bumped with them
+    pub stop_price: u64, // trigger price for stop and stop-limit orders
+    pub display_qty: u32, // iceberg peak, 0 shows the full shares_qty
     // Array of bytes last
-    pub _padding: [u8; 10], // padding to make it 48 bytes     
+    pub _reserved: [u8; 4], // padding to make it 64 bytes (one cache line)
 }
 
-impl Default for ShmOrder {
-    fn default() -> Self {
-        ShmOrder {
-            order_id: 0,
-            client_id: 0,
-            symbol: 0,
-            shares_qty: 0,
-            price: 0,
-            side: 0,
-            timestamp: 0,
-            status: 0,
-            _padding: [0; 10],
-        }
+impl ShmOrder{
+    pub const NEW : u8 = 0;
+    pub const CANCEL : u8 = 1;
+    pub const AMEND : u8 = 2;
+    pub const SESSION : u8 = 3;
+
+    // asks the engine to take `order_id` off the book for `symbol`
+    pub fn cancel(order_id : u64 , symbol : u32 , client_id : u32 , timestamp : u64)->Self{
+        Self { msg_type : Self::CANCEL , order_id , symbol , client_id , timestamp , ..Default::default() }
     }
-}
\ No newline at end of file
+
+    // moves the book for `symbol` to another trading phase , see SessionState::from_u8
+    pub fn session(symbol : u32 , session_state : u8 , timestamp : u64)->Self{
+        Self { msg_type : Self::SESSION , symbol , session_state , timestamp , ..Default::default() }
+    }
+
+    // asks the engine to change `order_id` to `price` and a total of `shares_qty`
+    pub fn amend(order_id : u64 , symbol : u32 , client_id : u32 , price : u64 , shares_qty : u32 , timestamp : u64)->Self{
+        Self { msg_type : Self::AMEND , order_id , symbol , client_id , price , shares_qty , timestamp , ..Default::default() }
+    }
+}
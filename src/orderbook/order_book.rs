@@ -1,19 +1,54 @@
 use crate::orderbook::book::BookSide;
-use crate::orderbook::order::{ Order, Side };
-
-use std::collections::VecDeque;
-use crate::orderbook::order_manager::OrderManager;
+use crate::orderbook::order::{ Order, PostOnly, Side, TimeInForce, Type };
+use crate::orderbook::stop_book::StopBook;
+use crate::orderbook::matching::{Fifo, MatchingPolicy};
+use crate::orderbook::auction;
+use crate::orderbook::session::{SessionAction, SessionState};
+use crate::orderbook::price_band::PriceBands;
+use crate::orderbook::instrument::Instrument;
+
+use crate::orderbook::order_manager::{OrderKey, OrderManager};
+use crate::orderbook::price_level::PriceLevel;
 use std::sync::atomic::{ AtomicU64, Ordering};
-use crate::orderbook::types::{Fill , Fills , MatchResult  , OrderBookError};
+use crate::orderbook::types::{AuctionIndication , AuctionKind , AuctionResult , Bbo , BboChanged , Fill , Fills , MatchResult  , OrderAmended , OrderBookError , OrderCancelled , OrderId , PostOnlyOutcome , PriceLevelChangedEvent , SelfTradePrevented , StopTriggered , StpPolicy , VolatilityInterruption};
 use crate::orderbook::iterator:: LevelsWithCumalativeDepth;
 
-#[derive(Debug)]
-pub struct PriceLevel{
-    pub total_volume : u64,
-    pub price : u64, 
-    pub orders : VecDeque<Order>,
+// client 0 is anonymous and never self trades 
+fn is_self_trade(taker : &Order , maker : &Order)->bool{
+    taker.client_id != 0 && taker.client_id == maker.client_id
 }
 
+// an incoming order met a resting order from the same client . the two never trade , the policy
+// decides which side loses quantity , a surviving maker keeps its place in the level 
+fn prevent_self_trade(policy : StpPolicy , level : &mut PriceLevel , manager : &mut OrderManager , taker : &mut Order , maker_key : OrderKey , self_trade : &mut SelfTradePrevented){
+    let maker = manager.all_orders[maker_key];
+    // an iceberg's reserve would have traded too , so the whole order counts and not just the peak 
+    self_trade.prevented_qty += taker.shares_qty.min(maker.total_qty());
+    let (cancel_taker , cancel_maker) = match policy{
+        StpPolicy::CancelNewest => (true , false),
+        StpPolicy::CancelOldest => (false , true),
+        StpPolicy::CancelBoth => (true , true),
+        StpPolicy::DecrementAndCancel => {
+            // both lose the smaller quantity , whichever reaches zero is cancelled 
+            let decrement = taker.shares_qty.min(maker.total_qty());
+            taker.shares_qty -= decrement;
+            self_trade.taker_cancelled_qty += decrement;
+            if decrement < maker.total_qty(){
+                level.reduce_order(maker_key, maker.total_qty() - decrement, manager);
+                self_trade.reduced_makers.push(OrderCancelled { cancelled_qty : decrement , ..OrderCancelled::new(&maker) });
+            }
+            (false , decrement == maker.total_qty())
+        }
+    };
+    if cancel_taker{
+        self_trade.taker_cancelled_qty += taker.shares_qty;
+        taker.shares_qty = 0;
+    }
+    if cancel_maker{
+        level.delete_order(maker.order_id, manager);
+        self_trade.cancelled_makers.push(OrderCancelled::new(&maker));
+    }
+}
 
 #[derive(Debug)]
 pub struct OrderBook{
@@ -21,7 +56,29 @@ pub struct OrderBook{
     pub askside : BookSide,
     pub bidside : BookSide,
     pub last_trade_price : AtomicU64,
-    pub manager : OrderManager
+    pub manager : OrderManager,
+    pub stops : StopBook,
+    // what happens when an incoming order meets a resting order from the same client 
+    pub stp_policy : StpPolicy,
+    // how an incoming order is shared between the orders resting at a price 
+    pub matching_policy : Box<dyn MatchingPolicy>,
+    // reference data every incoming order is checked against , its tick size is also how far post only orders slide 
+    pub instrument : Instrument,
+    // set while a call auction is collecting orders , nothing matches until it uncrosses 
+    pub auction : Option<AuctionKind>,
+    // trading phase , decides which messages the book accepts 
+    pub session : SessionState,
+    // static and dynamic price limits , off by default 
+    pub bands : PriceBands,
+    // when the running volatility auction uncrosses 
+    pub volatility_auction_end : Option<u64>,
+    // the engine's time for the message being handled , set by on_time . client timestamps can be anything ,
+    // so trading hours and the volatility auction run on this 
+    pub now : u64,
+    // top of the book , refreshed by everything that changes the book 
+    pub bbo : Bbo,
+    // the top as last handed out by take_bbo_change 
+    pub published_bbo : Bbo
 }
 
 impl OrderBook{
@@ -31,244 +88,447 @@ impl OrderBook{
             askside: BookSide::new(Side::Ask),
             bidside: BookSide::new(Side::Bid) ,
             last_trade_price: AtomicU64::new(0),
-            manager : OrderManager::new()
+            manager : OrderManager::new(),
+            stops : StopBook::new(),
+            stp_policy : StpPolicy::CancelNewest,
+            matching_policy : Box::new(Fifo),
+            instrument : Instrument::new(symbol, &symbol.to_string()),
+            auction : None,
+            session : SessionState::Continuous,
+            bands : PriceBands::default(),
+            volatility_auction_end : None,
+            now : 0,
+            bbo : Bbo::default(),
+            published_bbo : Bbo::default()
         }
     }
 
 
+    pub fn with_instrument(instrument : Instrument)->Self{
+        let mut book = Self::new(instrument.symbol);
+        book.instrument = instrument;
+        book
+    }
+
     pub fn insert_order(&mut self , order : Order ){
         match order.side {
             Side::Ask => self.askside.insert(order ,&mut self.manager) ,
             Side::Bid => self.bidside.insert(order ,&mut self.manager),
         }
+        self.refresh_bbo();
     }
 
-    pub fn match_market_order(&mut self , order:&mut Order )->Result<MatchResult , OrderBookError>{
-        // wejust need to fill the shares 
-        //if a very large maket order comes and there are not enough shares for it to eat , its canceled
+    // parks a stop or stop limit order until the last trade price reaches its stop price
+    pub fn add_stop_order(&mut self , order : Order)->Result<() , OrderBookError>{
+        self.check_session(SessionAction::NewOrder)?;
+        self.validate_new_order(&order)?;
+        self.check_instrument(Some(order.stop_price), order.total_qty())?;
+        if order.order_type == Type::StopLimit{
+            self.check_instrument(Some(order.price), order.total_qty())?;
+        }
+        self.stops.insert(order);
+        Ok(())
+    }
 
-        let opposite_side = match order.side{
-            Side::Ask => &mut self.bidside , 
-            Side::Bid => &mut self.askside,
-        };  // took a mutable refrence of the side owned by the orderbook 
+    pub fn check_session(&self , action : SessionAction)->Result<() , OrderBookError>{
+        if self.session.allows(action){
+            Ok(())
+        }
+        else {
+            Err(OrderBookError::ActionNotAllowed { state : self.session , action })
+        }
+    }
 
-        let mut fills = Fills::new();
+    // resting in the book or parked as a stop 
+    pub fn contains_order(&self , order_id : OrderId)->bool{
+        self.manager.id_to_key.contains_key(&order_id) || self.stops.contains(order_id)
+    }
 
-        while order.shares_qty > 0 {
-            let best_price = match opposite_side.get_best_price() {
-                Some(price) => price,
-                None => break 
-            };
-            // now we start fillig orders at this level 
-            let empty = {
-                let level = opposite_side.levels.get_mut(&best_price).unwrap();
-                // we got the price Level we start matchng 
-                while order.shares_qty > 0 && level.check_if_empty() == false{
-                    let  oldest_order_key = level.remove_oldest_order(&mut self.manager).unwrap();
-                    let ( shares , order_id ) = {
-                        let oldest_order =  self.manager.all_orders.get_mut(oldest_order_key).unwrap();
-                        (oldest_order.shares_qty , oldest_order.order_id )
-                    };
-
-                    if order.shares_qty >= shares{
-                        // then this order will be complete consumed 
-                        // TODO -> expose a function in the ordermanager that removes the order from the maps
-                        // we get a copy of shares , but we dont need to update the actual because we are deleting
-                        let consumed = shares;
-                        order.shares_qty = order.shares_qty.saturating_sub(shares);
-                        fills.add(Fill{
-                            price : best_price ,
-                            quantity : consumed , 
-                            taker_order_id : order.order_id,
-                            maker_order_id : order_id
-                        });
-                         self.manager.all_orders.remove(oldest_order_key);
-                         self.manager.id_to_key.remove(&order_id);
-                    }
-                    else {
-                        // if shares is more then then the market order is finished and then oldest order will
-                        // be chnaged and inserted at the head of the level not the tail 
-                        let consumed = order.shares_qty;
-                        self.manager.all_orders.get_mut(oldest_order_key).unwrap().shares_qty =  self.manager.all_orders.get_mut(oldest_order_key).unwrap().shares_qty.saturating_sub(consumed);
-                        // changed in the orignal map too 
-                        fills.add(Fill{
-                            price : best_price ,
-                            quantity : consumed , 
-                            taker_order_id : order.order_id,
-                            maker_order_id : order_id
-                        });
-                        order.shares_qty = 0 ; 
-                        // take a mutable refrence and update the shares and then insert 
-                        level.insert_at_head(oldest_order_key, &mut self.manager);
-                    }
+    // sanity checks every new order goes through before anything else looks at it 
+    pub fn validate_new_order(&self , order : &Order)->Result<() , OrderBookError>{
+        if order.total_qty() == 0{
+            return Err(OrderBookError::ZeroQuantity);
+        }
+        if matches!(order.order_type , Type::Limit | Type::StopLimit) && order.price == 0{
+            return Err(OrderBookError::ZeroPrice);
+        }
+        if matches!(order.order_type , Type::Stop | Type::StopLimit) && order.stop_price == 0{
+            return Err(OrderBookError::ZeroStopPrice);
+        }
+        // a second order under the same id would overwrite id_to_key and orphan the first 
+        if self.contains_order(order.order_id){
+            return Err(OrderBookError::DuplicateOrderId(order.order_id));
+        }
+        Ok(())
+    }
 
-                }
+    // inside trading hours , price on a tick , quantity a whole number of lots within the instrument's limits .
+    // market orders have no price to check 
+    pub fn check_instrument(&self , price : Option<u64> , qty : u32)->Result<() , OrderBookError>{
+        let instrument = &self.instrument;
+        if let Some(hours) = instrument.trading_hours && !hours.contains(self.now){
+            return Err(OrderBookError::OutsideTradingHours { open : hours.open , close : hours.close });
+        }
+        if let Some(price) = price && !instrument.is_on_tick(price){
+            return Err(OrderBookError::OffTick { price , tick_size : instrument.tick_size });
+        }
+        if !instrument.is_round_lot(qty){
+            return Err(OrderBookError::OddLot { qty , lot_size : instrument.lot_size });
+        }
+        if !instrument.qty_in_range(qty){
+            return Err(OrderBookError::QtyOutOfRange { qty , min_qty : instrument.min_qty , max_qty : instrument.max_qty });
+        }
+        Ok(())
+    }
 
-                level.check_if_empty()
-            };
+    // a limit price has to sit inside the static band 
+    pub fn check_price_band(&self , price : u64)->Result<() , OrderBookError>{
+        match self.bands.static_range(self.reference_price()){
+            Some((low , high)) if price < low || price > high => Err(OrderBookError::PriceOutsideBand { price , low , high }),
+            _ => Ok(())
+        }
+    }
 
-            if empty{
-                opposite_side.remove_level_if_empty(best_price);
+    // moves the book's clock to the engine's receive time of an incoming message and ends a volatility
+    // interruption once its period is over 
+    pub fn on_time(&mut self , now : u64)->Option<AuctionResult>{
+        self.now = self.now.max(now);
+        match self.volatility_auction_end{
+            Some(ends_at) if self.now >= ends_at && self.auction == Some(AuctionKind::Volatility) => {
+                let reference_price = self.reference_price();
+                Some(self.uncross(reference_price))
             }
-
+            _ => None
         }
-
-        Ok(MatchResult{
-            order_id : order.order_id , fills , remaining_qty:0
-        }) 
     }
 
+    // moves the book to another trading phase . entering pre open or pre close starts the auction call ,
+    // going to continuous or closed with an auction still collecting uncrosses it first
+    pub fn transition(&mut self , next : SessionState)->Result<Option<AuctionResult> , OrderBookError>{
+        if !self.session.can_transition_to(next){
+            return Err(OrderBookError::IllegalTransition { from : self.session , to : next });
+        }
+        self.session = next;
+        match next{
+            SessionState::PreOpen => self.start_auction(AuctionKind::Opening),
+            SessionState::PreClose => self.start_auction(AuctionKind::Closing),
+            SessionState::Continuous | SessionState::Closed if self.auction.is_some() => {
+                let reference_price = self.reference_price();
+                return Ok(Some(self.uncross(reference_price)));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
 
-    pub fn match_bid(&mut self , order: &mut Order)->Result<MatchResult , OrderBookError>{
-       // println!("recived the order , matching now");
-        
-        let mut fills =  Fills::new();
-        let opposite_side = &mut  self.askside ;
-        // we have a bid to match , the best price shud be the loweest ask 
-        
-        while order.shares_qty > 0 {
-            let best_price = match opposite_side.get_best_price(){
-                Some(price) => price,
+    // fires every stop the last trade price has reached , in priority order
+    // the trades printed by one activation can fire further stops , so this loops until nothing is left to trigger
+    // a stop that cannot be matched once it fires is dropped with its reason , the ones after it still fire
+    pub fn trigger_stops(&mut self)->Vec<(StopTriggered , Result<MatchResult , OrderBookError>)>{
+        let mut activations = Vec::new();
+        loop {
+            let last_trade_price = self.last_trade_price.load(Ordering::Relaxed);
+            // nothing has traded yet , or nothing can trade until the auction uncrosses or the halt ends 
+            if last_trade_price == 0 || self.auction.is_some() || !self.session.allows(SessionAction::Match){
+                break;
+            }
+            let mut order = match self.stops.pop_triggered(last_trade_price){
+                Some(order) => order,
                 None => break
             };
-            if best_price > order.price{
-                break;
+            let trigger = StopTriggered{
+                order_id : order.order_id ,
+                side : order.side ,
+                stop_price : order.stop_price ,
+                last_trade_price
+            };
+            let match_result = match (order.order_type , order.side){
+                // the band moved since the stop was parked , its limit price can no longer trade 
+                (Type::StopLimit , _) if self.check_price_band(order.price).is_err() => Ok(MatchResult::killed(&mut order)),
+                (Type::StopLimit , Side::Bid) => {
+                    order.order_type = Type::Limit;
+                    self.match_bid(&mut order)
+                }
+                (Type::StopLimit , Side::Ask) => {
+                    order.order_type = Type::Limit;
+                    self.match_ask(&mut order)
+                }
+                _ => {
+                    order.order_type = Type::Market;
+                    self.match_market_order(&mut order)
+                }
+            };
+            activations.push((trigger , match_result));
+        }
+        activations
+    }
+
+    // a post only order must never take liquidity , if it would cross the touch it is
+    // either rejected or slid to one tick behind the opposite best price 
+    fn check_post_only(&mut self , order : &mut Order)->Option<PostOnlyOutcome>{
+        if order.post_only == PostOnly::Off{
+            return None;
+        }
+        let (touch , crosses) = match order.side{
+            Side::Bid => {
+                let best_ask = self.askside.get_best_price();
+                (best_ask , best_ask.is_some_and(|ask| order.price >= ask))
+            }
+            Side::Ask => {
+                let best_bid = self.bidside.get_best_price();
+                (best_bid , best_bid.is_some_and(|bid| order.price <= bid))
+            }
+        };
+        if !crosses{
+            return Some(PostOnlyOutcome::Rested);
+        }
+        if order.post_only == PostOnly::Reject{
+            return Some(PostOnlyOutcome::Rejected);
+        }
+        let touch = touch.unwrap();
+        let slid_price = match order.side{
+            Side::Bid => touch.checked_sub(self.instrument.tick_size).filter(|price| *price > 0),
+            Side::Ask => touch.checked_add(self.instrument.tick_size),
+        };
+        match slid_price{
+            Some(new_price) => {
+                let original_price = order.price;
+                order.price = new_price;
+                Some(PostOnlyOutcome::Repriced { original_price, new_price })
             }
+            // there is no price behind the touch to slide to 
+            None => Some(PostOnlyOutcome::Rejected)
+        }
+    }
 
-            let empty = {
-                let level = opposite_side.levels.get_mut(&best_price).unwrap();
-                // we got the price Level we start matchng 
-                while order.shares_qty > 0 && level.check_if_empty() == false{
-
-
-                    let  oldest_order_key = level.remove_oldest_order(&mut self.manager).unwrap();
-                    let ( shares , order_id ) = {
-                        let oldest_order = self.manager.all_orders.get_mut(oldest_order_key).unwrap();
-                        (oldest_order.shares_qty , oldest_order.order_id )
-                    };
-
-                    if order.shares_qty >= shares{
-                        // then this order will be complete consumed 
-                        // TODO -> expose a function in the ordermanager that removes the order from the maps
-                        // we get a copy of shares , but we dont need to update the actual because we are deleting
-                        let consumed = shares;
-                        order.shares_qty = order.shares_qty.saturating_sub(shares);
-                        fills.add(Fill{
-                            price : best_price ,
-                            quantity : consumed , 
-                            taker_order_id : order.order_id,
-                            maker_order_id : order_id
-                        });
-                        self.manager.all_orders.remove(oldest_order_key);
-                        self.manager.id_to_key.remove(&order_id);
-                    }
-                    else {
-                        // if shares is more then then the market order is finished and then oldest order will
-                        // be chnaged and inserted at the head of the level not the tail 
-                        let consumed = order.shares_qty;
-                        self.manager.all_orders.get_mut(oldest_order_key).unwrap().shares_qty -= consumed;
-                        // changed in the orignal map too 
-                        fills.add(Fill{
-                            price : best_price ,
-                            quantity : consumed , 
-                            taker_order_id : order.order_id,
-                            maker_order_id : order_id
-                        });
-                        order.shares_qty = 0 ; 
-                        // take a mutable refrence and update the shares and then insert 
-                        level.insert_at_head(oldest_order_key, &mut self.manager);
-                    }
+    pub fn match_market_order(&mut self , order:&mut Order )->Result<MatchResult , OrderBookError>{
+        // wejust need to fill the shares 
+        //if a very large maket order comes and there are not enough shares for it to eat , its canceled
+        self.check_session(SessionAction::NewOrder)?;
+        self.validate_new_order(order)?;
+        self.check_instrument(None, order.shares_qty)?;
+        // a market order has no price to queue at during an auction call 
+        if self.auction.is_some(){
+            return Ok(MatchResult::killed(order));
+        }
+        let accepts = |_level_price : u64| true;
+        if order.time_in_force == TimeInForce::Fok && self.fillable_qty(order, accepts) < order.shares_qty{
+            return Ok(MatchResult::killed(order));
+        }
+        let mut match_result = self.match_order(order, accepts);
+        // nothing of a market order rests , the unfilled part is cancelled
+        match_result.cancelled_qty += order.shares_qty;
+        match_result.remaining_qty = 0;
+        order.shares_qty = 0;
+        Ok(match_result)
+    }
 
-                }
+    pub fn match_bid(&mut self , order: &mut Order)->Result<MatchResult , OrderBookError>{
+        debug_assert_eq!(order.side , Side::Bid);
+        self.match_limit_order(order)
+    }
 
-                level.check_if_empty()
-            };
+    pub fn match_ask(&mut self , order: &mut Order)->Result<MatchResult , OrderBookError>{
+        debug_assert_eq!(order.side , Side::Ask);
+        self.match_limit_order(order)
+    }
 
-            if empty{
-                opposite_side.remove_level_if_empty(best_price);
-            }
+    // takes liquidity up to the limit price , then rests or cancels the rest depending on time in force 
+    fn match_limit_order(&mut self , order: &mut Order)->Result<MatchResult , OrderBookError>{
+        self.check_session(SessionAction::NewOrder)?;
+        self.validate_new_order(order)?;
+        self.check_instrument(Some(order.price), order.total_qty())?;
+        self.check_price_band(order.price)?;
+        // an incoming iceberg takes liquidity with its full size , the peak only matters once it rests 
+        order.merge_reserve();
+        if self.auction.is_some(){
+            return Ok(self.accumulate(order));
         }
+        let post_only = self.check_post_only(order);
+        if post_only == Some(PostOnlyOutcome::Rejected){
+            return Ok(MatchResult::post_only_rejected(order));
+        }
+        // a bid takes asks priced at or below its limit , an ask takes bids at or above it 
+        let (side , limit) = (order.side , order.price);
+        let accepts = move |level_price : u64| match side{
+            Side::Bid => level_price <= limit,
+            Side::Ask => level_price >= limit,
+        };
+        if order.time_in_force == TimeInForce::Fok && self.fillable_qty(order, accepts) < order.shares_qty{
+            return Ok(MatchResult::killed(order));
+        }
+
+        let mut match_result = self.match_order(order, accepts);
+        match_result.post_only = post_only;
         if order.shares_qty > 0 {
-            // this will go into the order book 
-            let remaining_order = Order{
-                order_id : order.order_id , 
-                side : order.side , 
-                shares_qty : order.shares_qty ,
-                timestamp : order.timestamp , 
-                price : order.price ,
-                next : None , 
-                prev : None,
-                symbol : order.symbol
-            };
-            self.bidside.insert(remaining_order , &mut self.manager);
+            if order.time_in_force == TimeInForce::Gtc{
+                // this will go into the order book 
+                let mut remaining_order = Order{ next : None , prev : None , ..*order };
+                remaining_order.split_reserve();
+                self.insert_order(remaining_order);
+            }
+            else {
+                // ioc leftovers never rest , so a post only ioc that did not cross has nothing to report 
+                match_result.cancelled_qty += order.shares_qty;
+                order.shares_qty = 0;
+                if match_result.post_only == Some(PostOnlyOutcome::Rested){
+                    match_result.post_only = None;
+                }
+            }
         }
-        if !fills.fills.is_empty(){
-            self.last_trade_price.store(
-                fills.fills.last().unwrap().price,
-                Ordering::Relaxed,
-            );
+        match_result.remaining_qty = order.shares_qty;
+        Ok(match_result)
+    }
+
+    // during an auction call orders only queue up , crossing or not . only a gtc order can wait for the uncross
+    fn accumulate(&mut self , order : &mut Order)->MatchResult{
+        if order.time_in_force != TimeInForce::Gtc{
+            return MatchResult::killed(order);
         }
+        let mut resting_order = Order{ next : None , prev : None , ..*order };
+        resting_order.split_reserve();
+        self.insert_order(resting_order);
+        MatchResult::new(order.order_id, order.shares_qty)
+    }
 
-        Ok(MatchResult{
-            order_id : order.order_id , fills , remaining_qty : order.shares_qty
-        })
+    // stops continuous matching and starts collecting orders for an opening or closing auction 
+    pub fn start_auction(&mut self , kind : AuctionKind){
+        self.auction = Some(kind);
+        self.volatility_auction_end = None;
     }
 
-    pub fn match_ask(&mut self , order: &mut Order)->Result<MatchResult , OrderBookError>{
-       // println!("recived the order , matching now");
+    // where the auction would uncross with the orders collected so far 
+    pub fn indicative_uncross(&self , reference_price : Option<u64>)->Option<AuctionIndication>{
+        auction::equilibrium(&self.bidside, &self.askside, reference_price)
+    }
+
+    // ends the auction call . every bid at or above and every ask at or below the equilibrium price
+    // trades at that price in price then time priority , what is left rests for continuous trading 
+    pub fn uncross(&mut self , reference_price : Option<u64>)->AuctionResult{
+        let kind = self.auction.take().unwrap_or(AuctionKind::Opening);
+        self.volatility_auction_end = None;
+        let indication = self.indicative_uncross(reference_price);
         let mut fills = Fills::new();
-        let opposite_side = &mut  self.bidside ;
-        // we have a bid to match , the best price shud be the loweest ask 
-        
+        if let Some(indication) = indication{
+            let price = indication.price;
+            let mut left = indication.matched_qty;
+            while left > 0{
+                let (Some(bid_price) , Some(ask_price)) = (self.bidside.get_best_price() , self.askside.get_best_price()) else {
+                    break;
+                };
+                if bid_price < price || ask_price > price{
+                    break;
+                }
+                self.bidside.touch(bid_price);
+                self.askside.touch(ask_price);
+                let bid_level = self.bidside.levels.get_mut(&bid_price).unwrap();
+                let ask_level = self.askside.levels.get_mut(&ask_price).unwrap();
+                let (bid_key , ask_key) = (bid_level.head.unwrap() , ask_level.head.unwrap());
+                let (buyer , seller) = (self.manager.all_orders[bid_key] , self.manager.all_orders[ask_key]);
+                let quantity = buyer.shares_qty.min(seller.shares_qty).min(left.min(u32::MAX as u64) as u32);
+                fills.add(Fill::new(price, quantity, buyer.order_id, seller.order_id));
+                bid_level.fill_order(bid_key, quantity, &mut self.manager);
+                ask_level.fill_order(ask_key, quantity, &mut self.manager);
+                self.bidside.remove_level_if_empty(bid_price);
+                self.askside.remove_level_if_empty(ask_price);
+                left -= quantity as u64;
+            }
+            if !fills.fills.is_empty(){
+                self.last_trade_price.store(price, Ordering::Relaxed);
+            }
+        }
+        self.refresh_bbo();
+        AuctionResult{ kind , indication , fills }
+    }
+
+    // what match_order would fill of `order` , under the same limits : it stops at the dynamic band edge ,
+    // a same client maker cancelled under CancelOldest gives nothing and any other policy costs the taker
+    // quantity there , so nothing behind it can be counted on 
+    fn fillable_qty(&self , order : &Order , accepts : impl Fn(u64)->bool)->u32{
+        let dynamic_range = self.bands.dynamic_range(self.reference_price());
+        let in_band = |price : u64| accepts(price) && dynamic_range.is_none_or(|(low , high)| price >= low && price <= high);
+        let stp_policy = self.stp_policy;
+        let offers = |maker : &Order| match is_self_trade(order, maker){
+            false => Some(maker.total_qty()),
+            true if stp_policy == StpPolicy::CancelOldest => Some(0),
+            true => None
+        };
+        let opposite_side = match order.side{
+            Side::Ask => &self.bidside , 
+            Side::Bid => &self.askside,
+        };
+        opposite_side.fillable_qty(&self.manager, in_band, offers, order.shares_qty)
+    }
+
+    // the one matching loop every order type goes through . walks the opposite side from its best price
+    // while `accepts` holds for the level price , filling against each level as the matching policy allocates
+    // leaves whatever did not fill in `order.shares_qty` , the caller decides if that rests or is cancelled
+    fn match_order(&mut self , order : &mut Order , accepts : impl Fn(u64)->bool)->MatchResult{
+        let mut fills = Fills::new();
+        let mut self_trade = SelfTradePrevented::default();
+        let mut volatility_interruption = None;
+        // the band stays around the price the book traded at before this order , so one order cannot drag it along 
+        let dynamic_reference = self.reference_price().or(self.bands.static_reference);
+        let dynamic_range = self.bands.dynamic_range(self.reference_price());
+        let opposite_side = match order.side{
+            Side::Ask => &mut self.bidside , 
+            Side::Bid => &mut self.askside,
+        };  // took a mutable refrence of the side owned by the orderbook 
+
         while order.shares_qty > 0 {
             let best_price = match opposite_side.get_best_price(){
                 Some(price) => price,
                 None => break
             };
-            if best_price < order.price{
+            if !accepts(best_price){
+                break;
+            }
+            if let Some((low , high)) = dynamic_range && (best_price < low || best_price > high){
+                volatility_interruption = Some(VolatilityInterruption{
+                    trigger_price : best_price ,
+                    reference_price : dynamic_reference.unwrap_or(best_price) ,
+                    low ,
+                    high ,
+                    ends_at : self.now.saturating_add(self.bands.volatility_auction_period)
+                });
                 break;
             }
 
+            opposite_side.touch(best_price);
             let empty = {
                 let level = opposite_side.levels.get_mut(&best_price).unwrap();
-                // we got the price Level we start matchng 
-                while order.shares_qty > 0 && level.check_if_empty() == false{
-                    let  oldest_order_key = level.remove_oldest_order(&mut self.manager).unwrap();
-                    let ( shares , order_id ) = {
-                        let oldest_order = self.manager.all_orders.get_mut(oldest_order_key).unwrap();
-                        (oldest_order.shares_qty , oldest_order.order_id )
-                    };
-
-                    if order.shares_qty >= shares{
-                        // we get a copy of shares , but we dont need to update the actual because we are deleting
-                        let consumed = shares;
-                        order.shares_qty = order.shares_qty.saturating_sub(shares);
-                        fills.add(Fill{
-                            price : best_price ,
-                            quantity : consumed , 
-                            taker_order_id : order.order_id,
-                            maker_order_id : order_id
-                        });
-                        self.manager.all_orders.remove(oldest_order_key);
-                        self.manager.id_to_key.remove(&order_id);
-                    }
-                    else {
-                        // if shares is more then then the market order is finished and then oldest order will
-                        // be chnaged and inserted at the head of the level not the tail 
-                        let consumed = order.shares_qty;
-                        self.manager.all_orders.get_mut(oldest_order_key).unwrap().shares_qty -= consumed;
-                        // changed in the orignal map too 
+                // we got the price Level we start matchng , the policy shares the incoming quantity
+                // across the level and we go again while both sides have something left
+                while order.shares_qty > 0 && !level.check_if_empty(){
+                    let up_to = if self.matching_policy.needs_full_level() { u32::MAX } else { order.shares_qty };
+                    let queue = level.queue(&self.manager, up_to);
+                    let resting : Vec<u32> = queue.iter().map(|(_ , shares)| *shares).collect();
+                    let allocations = self.matching_policy.allocate(order.shares_qty, &resting);
+
+                    for (&(maker_key , _) , allocated) in queue.iter().zip(allocations){
+                        if order.shares_qty == 0{
+                            break;
+                        }
+                        if is_self_trade(order, &self.manager.all_orders[maker_key]){
+                            prevent_self_trade(self.stp_policy, level, &mut self.manager, order, maker_key, &mut self_trade);
+                            continue;
+                        }
+                        let consumed = allocated.min(order.shares_qty);
+                        if consumed == 0{
+                            continue;
+                        }
+                        order.shares_qty -= consumed;
                         fills.add(Fill{
                             price : best_price ,
                             quantity : consumed , 
                             taker_order_id : order.order_id,
-                            maker_order_id : order_id
+                            maker_order_id : self.manager.all_orders[maker_key].order_id,
+                            aggressor_side : Some(order.side)
                         });
-                        order.shares_qty = 0 ; 
-                        // take a mutable refrence and update the shares and then insert 
-                        level.insert_at_head(oldest_order_key, &mut self.manager);
+                        // the maker keeps its place while anything is left , a used up iceberg refills
+                        // from its reserve and goes to the back of the level , anything else leaves the book
+                        level.fill_order(maker_key, consumed, &mut self.manager);
                     }
-
                 }
 
                 level.check_if_empty()
@@ -278,37 +538,64 @@ impl OrderBook{
                 opposite_side.remove_level_if_empty(best_price);
             }
         }
-        if order.shares_qty > 0 {
-            // this will go into the order book 
-            let remaining_order = Order{
-                order_id : order.order_id , 
-                side : order.side , 
-                shares_qty : order.shares_qty ,
-                timestamp : order.timestamp , 
-                price : order.price ,
-                next : None , 
-                prev : None,
-                symbol : order.symbol
-            };
-            self.askside.insert(remaining_order, &mut self.manager);
+
+        if let Some(last_fill) = fills.fills.last(){
+            self.last_trade_price.store(last_fill.price, Ordering::Relaxed);
         }
-        if !fills.fills.is_empty(){
-            self.last_trade_price.store(
-                fills.fills.last().unwrap().price,
-                Ordering::Relaxed,
-            );
+        self.refresh_bbo();
+        // whatever is left of the order waits for the uncross like any order sent during the call 
+        if let Some(interruption) = volatility_interruption{
+            self.start_auction(AuctionKind::Volatility);
+            self.volatility_auction_end = Some(interruption.ends_at);
+        }
+
+        MatchResult{
+            order_id : order.order_id , fills , remaining_qty : order.shares_qty ,
+            cancelled_qty : self_trade.taker_cancelled_qty , post_only : None , self_trade , volatility_interruption
         }
-        Ok(MatchResult{
-            order_id : order.order_id , fills , remaining_qty : order.shares_qty
-        })
     }
 
-    pub fn get_best_bid(&mut self)->Option<u64>{
-        self.bidside.get_best_price()
+    // L2 changes since the last call , bids then asks , each level at most once however often it changed 
+    pub fn take_level_updates(&mut self)->Vec<PriceLevelChangedEvent>{
+        let mut updates = self.bidside.take_level_updates(self.symbol);
+        updates.extend(self.askside.take_level_updates(self.symbol));
+        updates
+    }
+
+    pub fn get_best_bid(&self)->Option<u64>{
+        self.bbo.bid_price
+    }
+
+    pub fn get_best_ask(&self)->Option<u64>{
+        self.bbo.ask_price
+    }
+
+    pub fn get_bbo(&self)->Bbo{
+        self.bbo
+    }
+
+    // re-reads the top of both sides , only the ends of the level maps are looked at 
+    fn refresh_bbo(&mut self){
+        let (bid_price , bid_qty) = self.bidside.best_level().map_or((None , 0) , |(price , qty)| (Some(price) , qty));
+        let (ask_price , ask_qty) = self.askside.best_level().map_or((None , 0) , |(price , qty)| (Some(price) , qty));
+        self.bbo = Bbo { bid_price , bid_qty , ask_price , ask_qty };
+    }
+
+    // the top if it moved since the last call , price or size 
+    pub fn take_bbo_change(&mut self)->Option<BboChanged>{
+        if self.bbo == self.published_bbo{
+            return None;
+        }
+        self.published_bbo = self.bbo;
+        Some(BboChanged { symbol : self.symbol , bbo : self.bbo })
     }
 
-    pub fn get_best_ask(&mut self)->Option<u64>{
-        self.askside.get_best_price()
+    // last trade price once something has traded , used to break ties in an auction 
+    pub fn reference_price(&self)->Option<u64>{
+        match self.last_trade_price.load(Ordering::Relaxed){
+            0 => None,
+            price => Some(price)
+        }
     }
 
     pub fn get_last_trade_price(&self)->Option<u64>{
@@ -341,22 +628,97 @@ impl OrderBook{
 
     }
 
-    pub fn cancel_order(&mut self ,order_id : u64){
-        if let Some(&order_index) = self.manager.id_to_index.get(&order_id){
-             // we got the orderIndex 
-             let (side , price) = {
-                 let order = self.manager.get(order_index).unwrap();
-                 (order.side , order.price)
-             };
-             match side{
-                 Side::Ask => {
-                     self.askside.delete_order(price, &mut self.manager, order_id);
-                 },
-                 Side::Bid => {
-                     self.bidside.delete_order(price, &mut self.manager, order_id);
-                 }
-             }
+    // takes a resting or parked stop order off the book , returns what was cancelled 
+    pub fn cancel_order(&mut self ,order_id : u64)->Option<Order>{
+        if let Some(order) = self.stops.remove(order_id){
+            return Some(order);
         }
-     }
-}
+        let order_index = *self.manager.id_to_key.get(&order_id)?;
+        // we got the orderIndex 
+        let order = *self.manager.all_orders.get(order_index).unwrap();
+        match order.side{
+            Side::Ask => {
+                self.askside.delete_order(order.price, &mut self.manager, order_id);
+            },
+            Side::Bid => {
+                self.bidside.delete_order(order.price, &mut self.manager, order_id);
+            }
+        }
+        self.refresh_bbo();
+        Some(order)
+    }
 
+    // changes the price and/or total quantity of a resting order
+    // a quantity decrease at the same price is done in place and keeps priority , anything else is a
+    // cancel replace that can trade like a new order and otherwise rests at the back of its new level
+    // a new quantity of 0 just cancels . a parked stop is live but has no place in the book to amend 
+    pub fn amend_order(&mut self , order_id : OrderId , new_price : u64 , new_qty : u32)->Result<(OrderAmended , Option<MatchResult>) , OrderBookError>{
+        self.check_session(SessionAction::Amend)?;
+        if self.stops.contains(order_id){
+            return Err(OrderBookError::StopNotAmendable(order_id));
+        }
+        let order_key = match self.manager.id_to_key.get(&order_id){
+            Some(&order_key) => order_key,
+            None => return Err(OrderBookError::OrderNotFound(order_id))
+        };
+        let old_order = self.manager.all_orders[order_key];
+        let priority_kept = new_price == old_order.price && new_qty > 0 && new_qty <= old_order.total_qty();
+        // the replacement would be rejected , leave the original where it is 
+        if new_qty > 0{
+            if new_price == 0{
+                return Err(OrderBookError::ZeroPrice);
+            }
+            self.check_instrument(Some(new_price), new_qty)?;
+            // a replacement goes through the band like any new order , even at the old price 
+            if !priority_kept{
+                self.check_price_band(new_price)?;
+            }
+        }
+        let mut amended = OrderAmended{
+            order_id ,
+            side : old_order.side ,
+            old_price : old_order.price ,
+            old_qty : old_order.total_qty() ,
+            new_price ,
+            new_qty ,
+            priority_kept : false
+        };
+
+        if priority_kept{
+            let side = match old_order.side{
+                Side::Ask => &mut self.askside,
+                Side::Bid => &mut self.bidside,
+            };
+            side.touch(old_order.price);
+            side.levels.get_mut(&old_order.price).unwrap().reduce_order(order_key, new_qty, &mut self.manager);
+            self.refresh_bbo();
+            amended.priority_kept = true;
+            return Ok((amended , None));
+        }
+
+        self.cancel_order(order_id);
+        if new_qty == 0{
+            return Ok((amended , None));
+        }
+        let mut replacement = Order{
+            price : new_price ,
+            shares_qty : new_qty ,
+            hidden_qty : 0 ,
+            next : None ,
+            prev : None ,
+            ..old_order
+        };
+        let match_result = match replacement.side{
+            Side::Bid => self.match_bid(&mut replacement),
+            Side::Ask => self.match_ask(&mut replacement),
+        };
+        match match_result{
+            Ok(match_result) => Ok((amended , Some(match_result))),
+            // everything was checked above , should the book still refuse it the original goes back rather than being lost 
+            Err(e) => {
+                self.insert_order(Order{ next : None , prev : None , ..old_order });
+                Err(e)
+            }
+        }
+    }
+}
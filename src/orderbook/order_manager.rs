@@ -4,7 +4,7 @@ use crate::orderbook::order::Order;
 
 new_key_type! { pub  struct  OrderKey; }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct OrderManager{
     pub all_orders : SlotMap<OrderKey , Order>,
     pub id_to_key : HashMap<u64  , OrderKey>
@@ -18,6 +18,20 @@ impl OrderManager{
         }
     }
 
-    
+    // puts the order in the slotmap and indexes it by its id 
+    // the book refuses live duplicates before they get here , a second insert would orphan the first order 
+    pub fn insert_order(&mut self , order : Order)->OrderKey{
+        let order_id = order.order_id;
+        debug_assert!(!self.id_to_key.contains_key(&order_id), "order id {} is already live", order_id);
+        let order_key = self.all_orders.insert(order);
+        self.id_to_key.insert(order_id, order_key);
+        order_key
+    }
+
+    // drops the order from both maps , does not touch the price level links 
+    pub fn remove_order(&mut self , order_id : u64)->Option<Order>{
+        let order_key = self.id_to_key.remove(&order_id)?;
+        self.all_orders.remove(order_key)
+    }
 }
 
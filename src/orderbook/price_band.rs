@@ -0,0 +1,48 @@
+// limits on how far prices can move , both widths are in basis points of their reference and 0 turns a band off
+// static bands sit around a fixed reference ( e.g. the previous close ) , orders priced outside them are rejected
+// dynamic bands follow the last trade , a trade that would print outside them interrupts continuous trading
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PriceBands{
+    pub static_bps : u64 ,
+    // falls back to the last trade price when not set 
+    pub static_reference : Option<u64> ,
+    pub dynamic_bps : u64 ,
+    // how long a volatility interruption auction collects orders , same unit as order timestamps 
+    pub volatility_auction_period : u64
+}
+
+impl PriceBands{
+    // inclusive (low , high) , None when the band is off or there is nothing to center it on
+    pub fn range(reference : Option<u64> , bps : u64)->Option<(u64 , u64)>{
+        let reference = reference?;
+        if bps == 0{
+            return None;
+        }
+        let width = (reference as u128 * bps as u128 / 10_000) as u64;
+        Some((reference.saturating_sub(width) , reference.saturating_add(width)))
+    }
+
+    pub fn static_range(&self , last_trade_price : Option<u64>)->Option<(u64 , u64)>{
+        Self::range(self.static_reference.or(last_trade_price), self.static_bps)
+    }
+
+    pub fn dynamic_range(&self , last_trade_price : Option<u64>)->Option<(u64 , u64)>{
+        Self::range(last_trade_price.or(self.static_reference), self.dynamic_bps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_band_ranges() {
+        let bands = PriceBands { static_bps: 1000, static_reference: Some(100), dynamic_bps: 200, volatility_auction_period: 0 };
+        assert_eq!(bands.static_range(Some(150)), Some((90, 110)));
+        assert_eq!(bands.dynamic_range(Some(150)), Some((147, 153)));
+        // nothing traded yet , the dynamic band starts from the static reference
+        assert_eq!(bands.dynamic_range(None), Some((98, 102)));
+        assert_eq!(PriceBands::default().static_range(Some(100)), None);
+        assert_eq!(PriceBands::range(None, 100), None);
+    }
+}
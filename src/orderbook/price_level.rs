@@ -6,6 +6,10 @@ use crate::orderbook::order_manager::OrderManager;
 pub struct PriceLevel{
     pub price : u64 , 
     pub total_vol : u32 , 
+    // iceberg reserve resting at this price , not part of the displayed volume 
+    pub hidden_vol : u32 ,
+    // orders linked into the level , what an L2 update reports next to the volume 
+    pub order_count : u32 ,
     pub head : Option<OrderKey>,
     pub tail : Option<OrderKey>
 }
@@ -16,6 +20,8 @@ impl PriceLevel{
         Self{
             price ,
             total_vol: 0 , 
+            hidden_vol : 0 ,
+            order_count : 0 ,
             head : None , 
             tail : None , 
    
@@ -24,10 +30,13 @@ impl PriceLevel{
     // when a limitn order cannot be fuldilled it will be required to be inserted in the order book
 
     pub fn add_order(&mut self ,  manager :&mut OrderManager ,   order:Order){
-        let order_key = manager.all_orders.insert(order);
-        let order_id = manager.all_orders[order_key].order_id;
-        manager.id_to_key.insert(order_id, order_key);
+        let order_key = manager.insert_order(order);
+        self.hidden_vol += order.hidden_qty;
+        self.push_back(order_key, manager);
+    }
 
+    // links an order that is already in the maps at the tail of the level 
+    fn push_back(&mut self , order_key : OrderKey , manager : &mut OrderManager){
         // if this is the first order , this wud be the head and this wud be the tail
         match self.tail{
             None => {
@@ -55,159 +64,117 @@ impl PriceLevel{
         if let Some(curr_order) = manager.all_orders.get(order_key) {
             self.total_vol += curr_order.shares_qty;
         }
+        self.order_count += 1;
 
     }
 
+    // an iceberg whose peak was just consumed takes a new peak from its reserve and
+    // is requeued at the tail , losing its time priority . false if there is no reserve left
+    pub fn replenish(&mut self , order_key : OrderKey , manager : &mut OrderManager)->bool{
+        let refill = {
+            let order = manager.all_orders.get_mut(order_key).unwrap();
+            if order.hidden_qty == 0{
+                return false;
+            }
+            let refill = order.peak_qty.min(order.hidden_qty);
+            order.hidden_qty -= refill;
+            order.shares_qty = refill;
+            refill
+        };
+        self.hidden_vol = self.hidden_vol.saturating_sub(refill);
+        self.push_back(order_key, manager);
+        true
+    }
+
     pub fn delete_order(&mut self , order_id : u64 , manager : &mut OrderManager){
         // we get an order to remove 
         // that order will arl be there in the map 
         // it wud be there on the book 
         let order_key = manager.id_to_key[&order_id];
-            // there is only one order in the list 
-        if self.head == self.tail {
-            // took a mutable refeence in a scope and extracted the value we needed 
-            let shares = {
-                let order_to_del = manager.all_orders.get_mut(order_key).unwrap();
-                order_to_del.shares_qty
-            };
-            self.head = None;
-            self.tail = None;
-            self.total_vol = self.total_vol.saturating_sub(shares);
-            manager.all_orders.remove(order_key);
+        self.hidden_vol = self.hidden_vol.saturating_sub(manager.all_orders[order_key].hidden_qty);
+        self.unlink(order_key, manager);
+        manager.remove_order(order_id);
+    }
+
+    // takes an order out of the linked list wherever it sits , it stays in the maps 
+    fn unlink(&mut self , order_key : OrderKey , manager : &mut OrderManager){
+        let (prev_order_key , next_order_key , shares) = {
+            let order = manager.all_orders.get_mut(order_key).unwrap();
+            let links = (order.prev , order.next , order.shares_qty);
+            order.prev = None;
+            order.next = None;
+            links
+        };
+        // fix up the neighbours , a missing neighbour means we were the head or the tail 
+        match prev_order_key{
+            Some(prev_key) => manager.all_orders.get_mut(prev_key).unwrap().next = next_order_key,
+            None => self.head = next_order_key,
         }
-        // if the order to be deleted is the head of the list 
-        else if self.head == Some(order_key){
-            // extracted what all we needed 
-            let (shares , next_order_key ) = {
-                let order_to_del = manager.all_orders.get_mut(order_key).unwrap();
-                (order_to_del.shares_qty , order_to_del.next )
-            };
-            self.head = next_order_key;
-            if let Some(new_head_key) = self.head{
-                let new_head = manager.all_orders.get_mut(new_head_key).unwrap();
-                new_head.prev = None;
-            }
-            self.total_vol = self.total_vol.saturating_sub(shares);
-            manager.all_orders.remove(order_key);
+        match next_order_key{
+            Some(next_key) => manager.all_orders.get_mut(next_key).unwrap().prev = prev_order_key,
+            None => self.tail = prev_order_key,
         }
-        // if the order to be deleted is the tail of the list 
-        else if self.tail == Some(order_key){
-            let (shares  , prev_order_key) = {
-                let order_to_del = manager.all_orders.get_mut(order_key).unwrap();
-                (order_to_del.shares_qty  , order_to_del.prev)
-            };
-            self.tail = prev_order_key; 
-            if let Some(new_tail_key) = self.tail{
-                let new_tail = manager.all_orders.get_mut(new_tail_key).unwrap();
-                new_tail.next = None;
-            }
-            self.total_vol = self.total_vol.saturating_sub(shares);
-            manager.all_orders.remove(order_key);
+        self.total_vol = self.total_vol.saturating_sub(shares);
+        self.order_count = self.order_count.saturating_sub(1);
+    }
+
+    // takes `qty` off a resting order in place , it keeps its place in the queue while anything is left
+    // a used up iceberg refills from its reserve and goes to the back of the level
+    // returns true once the order is gone from the book 
+    pub fn fill_order(&mut self , order_key : OrderKey , qty : u32 , manager : &mut OrderManager)->bool{
+        let shares = {
+            let order = manager.all_orders.get_mut(order_key).unwrap();
+            order.shares_qty = order.shares_qty.saturating_sub(qty);
+            order.shares_qty
+        };
+        self.total_vol = self.total_vol.saturating_sub(qty);
+        if shares > 0{
+            return false;
         }
-        else{
-            // in the middle 
-            //{let prev_order_key = order_to_delete.prev.unwrap();} // we are still using order to del here , a mutable refrence to the map 
-            //let next_order_key = order_to_delete.next.unwrap();
-            let (prev_order_key , next_order_key , shares)={
-                let order_to_del = manager.all_orders.get_mut(order_key).unwrap();
-                (order_to_del.prev , order_to_del.next , order_to_del.shares_qty)
-            };
-
-            if prev_order_key.is_some() && next_order_key.is_some(){
-                {
-                    let prev_order = manager.all_orders.get_mut(prev_order_key.unwrap()).unwrap();  // we cant call this here 
-                    prev_order.next = next_order_key;
-                }
-
-                {
-                    let next_order = manager.all_orders.get_mut(next_order_key.unwrap()).unwrap();
-                    next_order.prev = prev_order_key ; 
-                }
-            }
-           // remoe the shares queantity 
-            // this line wont work because we have alr a ref to order.del
-            //self.total_vol = self.total_vol.saturating_sub(order_to_delete.shares_qty);
-            self.total_vol = self.total_vol.saturating_sub(shares);
-            manager.all_orders.remove(order_key);
+        self.unlink(order_key, manager);
+        if self.replenish(order_key, manager){
+            return false;
         }
-        
-        manager.id_to_key.remove(&order_id);
+        let order_id = manager.all_orders[order_key].order_id;
+        manager.remove_order(order_id);
+        true
+    }
 
-       // if self.head.is_none() && self.tail.is_none(){
-       //     self.empty = true;
-       // }
+    // the orders at this level in time priority with their displayed quantity
+    // stops early once the collected quantity reaches `up_to`
+    pub fn queue(&self , manager : &OrderManager , up_to : u32)->Vec<(OrderKey , u32)>{
+        let mut queue = Vec::new();
+        let mut collected = 0u32;
+        let mut current = self.head;
+        while let Some(order_key) = current{
+            if collected >= up_to{
+                break;
+            }
+            let order = &manager.all_orders[order_key];
+            queue.push((order_key , order.shares_qty));
+            collected = collected.saturating_add(order.shares_qty);
+            current = order.next;
+        }
+        queue
+    }
 
+    // shrinks a resting order to `new_qty` without touching its place in the queue
+    // for an iceberg the reserve is cut first so the displayed peak stays as long as possible
+    pub fn reduce_order(&mut self , order_key : OrderKey , new_qty : u32 , manager : &mut OrderManager){
+        let (from_hidden , from_visible) = manager.all_orders.get_mut(order_key).unwrap().reduce_to(new_qty);
+        self.hidden_vol = self.hidden_vol.saturating_sub(from_hidden);
+        self.total_vol = self.total_vol.saturating_sub(from_visible);
     }
 
     pub fn get_total_volume(&self )->u32{
         self.total_vol
     }
-    pub fn remove_oldest_order(&mut self , manager : &mut OrderManager)->Option<OrderKey>{
-        // we need to return the order key of the order present at head 
-        match self.head{
-            None => None  ,
-            Some(head_key )=>{
-                let (shares , next_order_key , order_id) = {
-                    let head_order = manager.all_orders.get_mut(head_key).unwrap();
-                    (head_order.shares_qty , head_order.next , head_order.order_id)
-                };
-                self.head = next_order_key;
-                if let Some(new_head_key) = next_order_key{
-                    let new_head_order= manager.all_orders.get_mut(new_head_key).unwrap();
-                    new_head_order.prev = None;
-                }
-                if self.tail == Some(head_key) {
-                    self.tail = None;
-                }
-
-                self.total_vol = self.total_vol.saturating_sub(shares);
-                // Clean up the popped order's links
-                if let Some(order) = manager.all_orders.get_mut(head_key) {
-                    order.prev = None;
-                    order.next = None;
-                }
-                Some(head_key)
-            }
-        }
-        
-    }
-
     pub fn check_if_empty(&self)->bool{
         if self.head.is_none() && self.tail.is_none(){
             return true;
         }
         false
     }
-    // This function reinserts at the head if an order was removed and only some shares of that order weere matched
-    pub fn insert_at_head(&mut self , order_key : OrderKey , manager : &mut OrderManager){
-        // it maybe the only order which was popped and needs to be reiserted 
-        // the order wasent removed from the maps so we cant the orderkey from the Id ;
-        let shares = {
-            let order = manager.all_orders.get_mut(order_key).unwrap();
-            order.shares_qty
-        };
-        match self.head{
-            // if self .head is empty that means itwas the last order at that price 
-            None => {
-                self.head = Some(order_key) ;
-                self.tail = Some(order_key) ; 
-            }
-
-            Some(old_head_key)=>{
-                // if there is a head order 
-                if let Some(order) = manager.all_orders.get_mut(order_key) {
-                    order.prev = None;
-                    order.next = Some(old_head_key);
-                }
-                if let Some(old_head) = manager.all_orders.get_mut(old_head_key) {
-                    old_head.prev = Some(order_key);
-                }
-                self.head = Some(order_key);
-            }
-        }
-
-        self.total_vol += shares;
-    }
 
 
     
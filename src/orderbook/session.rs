@@ -0,0 +1,84 @@
+// trading phase of one order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState{
+    // orders collect for the opening auction
+    PreOpen ,
+    // normal matching
+    Continuous ,
+    // orders collect for the closing auction
+    PreClose ,
+    // trading stopped , resting orders can only be cancelled
+    Halted ,
+    // nothing trades until the next pre open
+    Closed
+}
+
+// what a message asks the book to do , checked against the session state before anything happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction{
+    NewOrder ,
+    Amend ,
+    Cancel ,
+    // an incoming order trading against the book straight away
+    Match
+}
+
+impl SessionState{
+    pub fn allows(self , action : SessionAction)->bool{
+        match (self , action){
+            // a client can always take its orders off the book
+            (_ , SessionAction::Cancel) => true,
+            (SessionState::Continuous , _) => true,
+            (SessionState::PreOpen | SessionState::PreClose , SessionAction::NewOrder | SessionAction::Amend) => true,
+            _ => false
+        }
+    }
+
+    pub fn can_transition_to(self , next : SessionState)->bool{
+        matches!((self , next) ,
+            (SessionState::Closed , SessionState::PreOpen)
+            | (SessionState::PreOpen , SessionState::Continuous | SessionState::Halted | SessionState::Closed)
+            | (SessionState::Continuous , SessionState::PreClose | SessionState::Halted | SessionState::Closed)
+            | (SessionState::PreClose , SessionState::Closed | SessionState::Halted)
+            | (SessionState::Halted , SessionState::Continuous | SessionState::PreOpen | SessionState::Closed)
+        )
+    }
+
+    // wire encoding used by session messages on the queue
+    pub fn from_u8(state : u8)->Option<Self>{
+        match state{
+            0 => Some(SessionState::PreOpen),
+            1 => Some(SessionState::Continuous),
+            2 => Some(SessionState::PreClose),
+            3 => Some(SessionState::Halted),
+            4 => Some(SessionState::Closed),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halted_only_allows_cancels() {
+        assert!(SessionState::Halted.allows(SessionAction::Cancel));
+        assert!(!SessionState::Halted.allows(SessionAction::NewOrder));
+        assert!(!SessionState::Halted.allows(SessionAction::Amend));
+        assert!(!SessionState::Halted.allows(SessionAction::Match));
+        assert!(SessionState::PreOpen.allows(SessionAction::NewOrder));
+        assert!(!SessionState::PreOpen.allows(SessionAction::Match));
+        assert!(!SessionState::Closed.allows(SessionAction::NewOrder));
+    }
+
+    #[test]
+    fn test_transitions() {
+        assert!(SessionState::Closed.can_transition_to(SessionState::PreOpen));
+        assert!(!SessionState::Closed.can_transition_to(SessionState::Continuous));
+        assert!(SessionState::Continuous.can_transition_to(SessionState::Halted));
+        assert!(SessionState::Halted.can_transition_to(SessionState::Continuous));
+        assert!(!SessionState::PreClose.can_transition_to(SessionState::Continuous));
+        assert!(!SessionState::Continuous.can_transition_to(SessionState::Continuous));
+    }
+}
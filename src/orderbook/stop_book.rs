@@ -0,0 +1,86 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::orderbook::order::{Order, Side};
+use crate::orderbook::types::OrderId;
+
+// stop and stop limit orders waiting for the last trade price to reach their stop price
+// they are kept out of the matching book until they fire
+#[derive(Debug, Default)]
+pub struct StopBook{
+    // buy stops fire when the last trade is at or above the stop price , lowest stop fires first
+    pub buy_stops : BTreeMap<u64 , VecDeque<(u64 , Order)>>,
+    // sell stops fire when the last trade is at or below the stop price , highest stop fires first
+    pub sell_stops : BTreeMap<u64 , VecDeque<(u64 , Order)>>,
+    id_to_stop : HashMap<OrderId , (Side , u64)>,
+    // arrival sequence , breaks ties between a buy and a sell stop firing on the same print
+    next_seq : u64
+}
+
+impl StopBook{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    pub fn insert(&mut self , order : Order){
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let stops = match order.side{
+            Side::Bid => &mut self.buy_stops,
+            Side::Ask => &mut self.sell_stops,
+        };
+        stops.entry(order.stop_price).or_default().push_back((seq , order));
+        self.id_to_stop.insert(order.order_id, (order.side , order.stop_price));
+    }
+
+    pub fn remove(&mut self , order_id : OrderId)->Option<Order>{
+        let (side , stop_price) = self.id_to_stop.remove(&order_id)?;
+        let stops = match side{
+            Side::Bid => &mut self.buy_stops,
+            Side::Ask => &mut self.sell_stops,
+        };
+        let queue = stops.get_mut(&stop_price)?;
+        let position = queue.iter().position(|(_ , order)| order.order_id == order_id)?;
+        let (_ , order) = queue.remove(position)?;
+        if queue.is_empty(){
+            stops.remove(&stop_price);
+        }
+        Some(order)
+    }
+
+    pub fn contains(&self , order_id : OrderId)->bool{
+        self.id_to_stop.contains_key(&order_id)
+    }
+
+    pub fn len(&self)->usize{
+        self.id_to_stop.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.id_to_stop.is_empty()
+    }
+
+    // takes out the highest priority stop that the last trade price has reached , if any
+    pub fn pop_triggered(&mut self , last_trade_price : u64)->Option<Order>{
+        let buy_seq = self.buy_stops.iter().next()
+            .filter(|(stop_price , _)| **stop_price <= last_trade_price)
+            .and_then(|(_ , queue)| queue.front())
+            .map(|(seq , _)| *seq);
+        let sell_seq = self.sell_stops.iter().next_back()
+            .filter(|(stop_price , _)| **stop_price >= last_trade_price)
+            .and_then(|(_ , queue)| queue.front())
+            .map(|(seq , _)| *seq);
+
+        let mut entry = match (buy_seq , sell_seq){
+            (None , None) => return None,
+            (Some(_) , None) => self.buy_stops.first_entry()?,
+            (None , Some(_)) => self.sell_stops.last_entry()?,
+            (Some(buy) , Some(sell)) if buy < sell => self.buy_stops.first_entry()?,
+            (Some(_) , Some(_)) => self.sell_stops.last_entry()?,
+        };
+        let (_ , order) = entry.get_mut().pop_front()?;
+        if entry.get().is_empty(){
+            entry.remove();
+        }
+        self.id_to_stop.remove(&order.order_id);
+        Some(order)
+    }
+}
@@ -1,5 +1,10 @@
 use crate::orderbook::order_book::OrderBook;
-use crate::orderbook::order::{Order,Side};
+use crate::orderbook::order::{Order,PostOnly,Side,TimeInForce};
+use crate::orderbook::types::{AuctionKind, Bbo, OrderBookError, PostOnlyOutcome, StpPolicy};
+use crate::orderbook::session::SessionState;
+use crate::orderbook::price_band::PriceBands;
+use crate::orderbook::instrument::{Instrument, TradingHours};
+use crate::orderbook::matching::{ProRata, TopOrderProRata};
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +80,626 @@ mod tests {
         assert_eq!(book.askside.levels.len(), 1); // 105 level removed
     }
 
+    #[test]
+    fn test_market_order_remainder_is_cancelled() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(31, Side::Bid, 40, 99, 31, 1));
+
+        let mut market_ask = Order::market(32, Side::Ask, 100, 32, 1);
+        let result = book.match_market_order(&mut market_ask).unwrap();
+        assert_eq!(result.fills.fills.len(), 1);
+        assert_eq!(result.fills.fills[0].price, 99);
+        assert_eq!(result.remaining_qty, 0);
+        assert_eq!(result.cancelled_qty, 60);
+        // the remainder must not rest on either side
+        assert_eq!(book.get_best_bid(), None);
+        assert_eq!(book.get_best_ask(), None);
+    }
+
+    #[test]
+    fn test_ioc_limit_never_rests() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(41, Side::Ask, 30, 100, 41, 1));
+        book.insert_order(new_order(42, Side::Ask, 30, 102, 42, 1));
+
+        let mut bid = new_order(43, Side::Bid, 100, 101, 43, 1);
+        bid.time_in_force = TimeInForce::Ioc;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.fills.fills.len(), 1);
+        assert_eq!(result.remaining_qty, 0);
+        assert_eq!(result.cancelled_qty, 70);
+        assert_eq!(book.get_best_bid(), None);
+        assert_eq!(book.get_best_ask(), Some(102));
+    }
+
+    #[test]
+    fn test_fok_is_all_or_nothing() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(51, Side::Bid, 30, 100, 51, 1));
+        book.insert_order(new_order(52, Side::Bid, 30, 99, 52, 1));
+        book.insert_order(new_order(53, Side::Bid, 30, 98, 53, 1));
+
+        // only 60 available at or above 99 , nothing may be touched
+        let mut ask = new_order(54, Side::Ask, 70, 99, 54, 1);
+        ask.time_in_force = TimeInForce::Fok;
+        let result = book.match_ask(&mut ask).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.cancelled_qty, 70);
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 30);
+        assert_eq!(book.get_best_ask(), None);
+
+        let mut ask = new_order(55, Side::Ask, 60, 99, 55, 1);
+        ask.time_in_force = TimeInForce::Fok;
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.fills.fills.len(), 2);
+        assert_eq!(result.cancelled_qty, 0);
+        assert_eq!(book.get_best_bid(), Some(98));
+
+        // the 120 ask is past the dynamic band , the match would stop at 100 so only 5 counts
+        let mut book = OrderBook::new(1);
+        book.bands = PriceBands { dynamic_bps: 500, static_reference: Some(100), volatility_auction_period: 1_000, ..Default::default() };
+        book.insert_order(new_order(56, Side::Ask, 5, 100, 56, 1));
+        book.insert_order(new_order(57, Side::Ask, 10, 120, 57, 1));
+        let mut bid = new_order(58, Side::Bid, 15, 120, 58, 1);
+        bid.time_in_force = TimeInForce::Fok;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert!(result.volatility_interruption.is_none());
+        assert_eq!(result.cancelled_qty, 15);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 5);
+        assert_eq!(book.auction, None);
+
+        // the taker's own ask is cancelled by stp , not traded , so only the other 10 counts
+        let mut book = OrderBook::new(1);
+        book.stp_policy = StpPolicy::CancelOldest;
+        book.insert_order(client_order(59, Side::Ask, 10, 100, 7));
+        book.insert_order(client_order(60, Side::Ask, 10, 100, 8));
+        let mut bid = client_order(61, Side::Bid, 20, 100, 7);
+        bid.time_in_force = TimeInForce::Fok;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert!(result.self_trade.cancelled_makers.is_empty());
+        assert_eq!(result.cancelled_qty, 20);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 20);
+        let mut bid = client_order(62, Side::Bid, 10, 100, 7);
+        bid.time_in_force = TimeInForce::Fok;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.fills.fills[0].maker_order_id, 60);
+        assert_eq!(result.cancelled_qty, 0);
+
+        // cancel newest would cancel the taker at its own ask , what rests behind it cannot be reached
+        let mut book = OrderBook::new(1);
+        book.insert_order(client_order(63, Side::Ask, 10, 100, 7));
+        book.insert_order(client_order(64, Side::Ask, 10, 100, 8));
+        let mut bid = client_order(65, Side::Bid, 10, 100, 7);
+        bid.time_in_force = TimeInForce::Fok;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.self_trade.prevented_qty, 0);
+        assert_eq!(result.cancelled_qty, 10);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 20);
+    }
+
+    #[test]
+    fn test_post_only_reject_and_slide() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(61, Side::Ask, 50, 105, 61, 1));
+
+        // would cross the 105 ask , rejected without trading
+        let mut bid = new_order(62, Side::Bid, 20, 105, 62, 1);
+        bid.post_only = PostOnly::Reject;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.post_only, Some(PostOnlyOutcome::Rejected));
+        assert_eq!(result.cancelled_qty, 20);
+        assert_eq!(book.get_best_bid(), None);
+
+        // slide mode rests one tick behind the touch instead
+        let mut bid = new_order(63, Side::Bid, 20, 107, 63, 1);
+        bid.post_only = PostOnly::Slide;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.post_only, Some(PostOnlyOutcome::Repriced { original_price: 107, new_price: 104 }));
+        assert_eq!(result.remaining_qty, 20);
+        assert_eq!(book.get_best_bid(), Some(104));
+        assert_eq!(book.askside.levels.get(&105).unwrap().get_total_volume(), 50);
+
+        // not crossing rests untouched
+        let mut ask = new_order(64, Side::Ask, 10, 106, 64, 1);
+        ask.post_only = PostOnly::Slide;
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.post_only, Some(PostOnlyOutcome::Rested));
+        assert_eq!(book.askside.levels.get(&106).unwrap().get_total_volume(), 10);
+
+        // an ioc that does not cross is cancelled , it never rested
+        let mut ask = new_order(65, Side::Ask, 10, 108, 65, 1);
+        ask.post_only = PostOnly::Reject;
+        ask.time_in_force = TimeInForce::Ioc;
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.post_only, None);
+        assert_eq!(result.cancelled_qty, 10);
+        assert!(!book.askside.levels.contains_key(&108));
+    }
+
+    #[test]
+    fn test_stop_orders_cascade_on_last_trade() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(71, Side::Ask, 10, 100, 71, 1));
+        book.insert_order(new_order(72, Side::Ask, 10, 102, 72, 1));
+        book.insert_order(new_order(73, Side::Ask, 10, 105, 73, 1));
+
+        // fires at 100 , lifts the 102 ask which in turn fires the 102 stop limit
+        book.add_stop_order(Order::stop(74, Side::Bid, 10, 100, 74, 1)).unwrap();
+        book.add_stop_order(Order::stop_limit(75, Side::Bid, 10, 106, 102, 75, 1)).unwrap();
+        // a sell stop far below must not fire
+        book.add_stop_order(Order::stop(76, Side::Ask, 10, 90, 76, 1)).unwrap();
+        assert!(book.trigger_stops().is_empty());
+
+        let mut bid = new_order(77, Side::Bid, 10, 100, 77, 1);
+        book.match_bid(&mut bid).unwrap();
+        let activations = book.trigger_stops();
+        assert_eq!(activations.len(), 2);
+        assert_eq!(activations[0].0.order_id, 74);
+        assert_eq!(activations[0].0.last_trade_price, 100);
+        assert_eq!(activations[0].1.as_ref().unwrap().fills.fills[0].price, 102);
+        assert_eq!(activations[1].0.order_id, 75);
+        assert_eq!(activations[1].0.last_trade_price, 102);
+        assert_eq!(activations[1].1.as_ref().unwrap().fills.fills[0].price, 105);
+        assert_eq!(book.get_last_trade_price(), Some(105));
+        assert_eq!(book.get_best_ask(), None);
+
+        // the sell stop is still parked and can be cancelled
+        assert!(book.stops.contains(76));
+        book.cancel_order(76);
+        assert!(book.stops.is_empty());
+    }
+
+    #[test]
+    fn test_stop_that_cannot_match_does_not_stop_the_rest() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(81, Side::Ask, 100, 100, 81, 1));
+        book.insert_order(new_order(82, Side::Ask, 100, 101, 82, 1));
+        book.add_stop_order(Order::stop(83, Side::Bid, 10, 100, 83, 1)).unwrap();
+        book.add_stop_order(Order::stop(84, Side::Bid, 100, 100, 84, 1)).unwrap();
+        // the lot size changed while the stops were parked , the first one is now an odd lot
+        book.instrument.lot_size = 100;
+
+        let mut bid = new_order(85, Side::Bid, 100, 100, 85, 1);
+        book.match_bid(&mut bid).unwrap();
+        let activations = book.trigger_stops();
+        assert_eq!(activations.len(), 2);
+        assert_eq!(activations[0].0.order_id, 83);
+        assert!(matches!(activations[0].1, Err(OrderBookError::OddLot { qty: 10, lot_size: 100 })));
+        assert_eq!(activations[1].0.order_id, 84);
+        assert_eq!(activations[1].1.as_ref().unwrap().fills.fills[0].price, 101);
+        assert!(book.stops.is_empty());
+    }
+
+    #[test]
+    fn test_parked_stop_cannot_be_amended() {
+        let mut book = OrderBook::new(1);
+        book.add_stop_order(Order::stop_limit(86, Side::Bid, 10, 101, 100, 86, 1)).unwrap();
+        // it is live , just not amendable , which is not the same answer as an unknown id
+        assert_eq!(book.amend_order(86, 102, 10).map(|_| ()), Err(OrderBookError::StopNotAmendable(86)));
+        assert_eq!(book.amend_order(87, 102, 10).map(|_| ()), Err(OrderBookError::OrderNotFound(87)));
+        assert!(book.contains_order(86));
+        assert_eq!(book.cancel_order(86).unwrap().order_id, 86);
+        assert!(book.stops.is_empty());
+    }
+
+    #[test]
+    fn test_iceberg_shows_peak_and_requeues_at_tail() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(Order::iceberg(81, Side::Ask, 100, 20, 105, 81, 1));
+        book.insert_order(new_order(82, Side::Ask, 30, 105, 82, 1));
+        // only the peak is displayed
+        let level = book.askside.levels.get(&105).unwrap();
+        assert_eq!(level.get_total_volume(), 50);
+        assert_eq!(level.hidden_vol, 80);
+        let (asks, _) = book.get_depth();
+        assert_eq!(asks[0][1], "50");
+
+        // eats the peak , the refill goes behind order 82
+        let mut bid = new_order(83, Side::Bid, 30, 105, 83, 1);
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.fills.fills.len(), 2);
+        assert_eq!(result.fills.fills[0].maker_order_id, 81);
+        assert_eq!(result.fills.fills[0].quantity, 20);
+        assert_eq!(result.fills.fills[1].maker_order_id, 82);
+        assert_eq!(result.fills.fills[1].quantity, 10);
+        let level = book.askside.levels.get(&105).unwrap();
+        assert_eq!(level.get_total_volume(), 40);
+        assert_eq!(level.hidden_vol, 60);
+
+        // a fill or kill may count on the hidden reserve
+        let mut bid = new_order(84, Side::Bid, 100, 105, 84, 1);
+        bid.time_in_force = TimeInForce::Fok;
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.cancelled_qty, 0);
+        assert_eq!(result.fills.fills.iter().map(|fill| fill.quantity).sum::<u32>(), 100);
+        assert_eq!(book.get_best_ask(), None);
+        assert!(book.manager.id_to_key.is_empty());
+    }
+
+    #[test]
+    fn test_amend_keeps_priority_only_on_decrease() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(91, Side::Bid, 50, 100, 91, 1));
+        book.insert_order(new_order(92, Side::Bid, 50, 100, 92, 1));
+
+        // decrease in place , 91 stays at the head
+        let (amended, result) = book.amend_order(91, 100, 20).unwrap();
+        assert!(amended.priority_kept);
+        assert_eq!((amended.old_qty, amended.new_qty), (50, 20));
+        assert!(result.is_none());
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 70);
+        let mut ask = new_order(93, Side::Ask, 10, 100, 93, 1);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.fills.fills[0].maker_order_id, 91);
+
+        // increase loses priority , 92 is now first
+        let (amended, _) = book.amend_order(91, 100, 40).unwrap();
+        assert!(!amended.priority_kept);
+        let mut ask = new_order(94, Side::Ask, 10, 100, 94, 1);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.fills.fills[0].maker_order_id, 92);
+
+        // reprice moves it to the new level and clears the old one when empty
+        book.amend_order(92, 99, 40).unwrap();
+        let (amended, _) = book.amend_order(91, 98, 40).unwrap();
+        assert_eq!((amended.old_price, amended.new_price), (100, 98));
+        assert!(!book.bidside.levels.contains_key(&100));
+        assert_eq!(book.get_best_bid(), Some(99));
+        assert_eq!(book.bidside.levels.get(&98).unwrap().get_total_volume(), 40);
+
+        assert!(book.amend_order(999, 100, 10).is_err());
+    }
+
+    fn client_order(order_id: u64, side: Side, qty: u32, price: u64, client_id: u32) -> Order {
+        let mut order = new_order(order_id, side, qty, price, order_id, 1);
+        order.client_id = client_id;
+        order
+    }
+
+    #[test]
+    fn test_self_trade_prevention_policies() {
+        // cancel newest , the resting order is untouched and the taker does not rest
+        let mut book = OrderBook::new(1);
+        book.insert_order(client_order(101, Side::Ask, 50, 100, 7));
+        let mut bid = client_order(102, Side::Bid, 30, 100, 7);
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.self_trade.prevented_qty, 30);
+        assert_eq!(result.cancelled_qty, 30);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 50);
+        assert_eq!(book.get_best_bid(), None);
+
+        // cancel oldest , the maker goes and the taker trades with the next order
+        let mut book = OrderBook::new(1);
+        book.stp_policy = StpPolicy::CancelOldest;
+        book.insert_order(client_order(111, Side::Ask, 50, 100, 7));
+        book.insert_order(client_order(112, Side::Ask, 50, 100, 8));
+        let mut bid = client_order(113, Side::Bid, 30, 100, 7);
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.fills.fills.len(), 1);
+        assert_eq!(result.fills.fills[0].maker_order_id, 112);
+        assert_eq!(result.self_trade.cancelled_makers[0].order_id, 111);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 20);
+        assert!(!book.manager.id_to_key.contains_key(&111));
+
+        // cancel both
+        let mut book = OrderBook::new(1);
+        book.stp_policy = StpPolicy::CancelBoth;
+        book.insert_order(client_order(121, Side::Bid, 50, 100, 7));
+        let mut ask = client_order(122, Side::Ask, 30, 100, 7);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.cancelled_qty, 30);
+        assert_eq!(result.self_trade.cancelled_makers.len(), 1);
+        assert_eq!(book.get_best_bid(), None);
+        assert_eq!(book.get_best_ask(), None);
+
+        // decrement and cancel , the larger resting order keeps the difference
+        let mut book = OrderBook::new(1);
+        book.stp_policy = StpPolicy::DecrementAndCancel;
+        book.insert_order(client_order(131, Side::Bid, 50, 100, 7));
+        let mut ask = client_order(132, Side::Ask, 30, 100, 7);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.cancelled_qty, 30);
+        assert!(result.self_trade.cancelled_makers.is_empty());
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 20);
+        assert_eq!(book.get_best_ask(), None);
+
+        // an iceberg maker counts with its reserve , the prevented quantity is what was taken off both
+        let mut book = OrderBook::new(1);
+        book.stp_policy = StpPolicy::DecrementAndCancel;
+        let mut iceberg = Order::iceberg(133, Side::Bid, 100, 20, 100, 133, 1);
+        iceberg.client_id = 7;
+        book.insert_order(iceberg);
+        let mut ask = client_order(134, Side::Ask, 50, 100, 7);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.self_trade.prevented_qty, 50);
+        assert_eq!(result.self_trade.taker_cancelled_qty, 50);
+        assert_eq!(result.self_trade.reduced_makers[0].cancelled_qty, 50);
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume() + book.bidside.levels.get(&100).unwrap().hidden_vol, 50);
+    }
+
+    #[test]
+    fn test_market_and_limit_paths_share_semantics() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(141, Side::Bid, 30, 101, 141, 1));
+        book.insert_order(new_order(142, Side::Bid, 30, 100, 142, 1));
+
+        // market path now prints the last trade like the limit paths
+        let mut market_ask = Order::market(143, Side::Ask, 40, 143, 1);
+        let result = book.match_market_order(&mut market_ask).unwrap();
+        assert_eq!(result.fills.fills.len(), 2);
+        assert_eq!(book.get_last_trade_price(), Some(100));
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 20);
+
+        // the limit path leaves the same partly filled maker at the head of its level
+        book.insert_order(new_order(144, Side::Bid, 10, 100, 144, 1));
+        let mut ask = new_order(145, Side::Ask, 25, 100, 145, 1);
+        let result = book.match_ask(&mut ask).unwrap();
+        assert_eq!(result.fills.fills[0].maker_order_id, 142);
+        assert_eq!(result.fills.fills[1].maker_order_id, 144);
+        assert_eq!(result.fills.fills[1].quantity, 5);
+        assert_eq!(result.remaining_qty, 0);
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 5);
+    }
+
+    #[test]
+    fn test_pro_rata_book_shares_a_level() {
+        let mut book = OrderBook::new(1);
+        book.matching_policy = Box::new(ProRata { min_allocation: 1 });
+        book.insert_order(new_order(151, Side::Ask, 50, 100, 151, 1));
+        book.insert_order(new_order(152, Side::Ask, 30, 100, 152, 1));
+        book.insert_order(new_order(153, Side::Ask, 20, 100, 153, 1));
+
+        let mut bid = new_order(154, Side::Bid, 10, 100, 154, 1);
+        let result = book.match_bid(&mut bid).unwrap();
+        let filled: Vec<(u64, u32)> = result.fills.fills.iter().map(|fill| (fill.maker_order_id, fill.quantity)).collect();
+        assert_eq!(filled, vec![(151, 5), (152, 3), (153, 2)]);
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 90);
+
+        // a sweep larger than the level empties it and carries on
+        book.insert_order(new_order(155, Side::Ask, 10, 101, 155, 1));
+        let mut bid = new_order(156, Side::Bid, 95, 101, 156, 1);
+        let result = book.match_bid(&mut bid).unwrap();
+        assert_eq!(result.fills.fills.iter().map(|fill| fill.quantity).sum::<u32>(), 95);
+        assert!(!book.askside.levels.contains_key(&100));
+        assert_eq!(book.askside.levels.get(&101).unwrap().get_total_volume(), 5);
+        assert!(book.manager.id_to_key.contains_key(&155));
+        assert_eq!(book.manager.id_to_key.len(), 1);
+
+        book.matching_policy = Box::new(TopOrderProRata { min_allocation: 0 });
+        book.insert_order(new_order(157, Side::Ask, 40, 101, 157, 1));
+        let mut bid = new_order(158, Side::Bid, 25, 101, 158, 1);
+        let result = book.match_bid(&mut bid).unwrap();
+        let filled: Vec<(u64, u32)> = result.fills.fills.iter().map(|fill| (fill.maker_order_id, fill.quantity)).collect();
+        assert_eq!(filled, vec![(155, 5), (157, 20)]);
+    }
+
+    #[test]
+    fn test_call_auction_accumulates_and_uncrosses() {
+        let mut book = OrderBook::new(1);
+        book.start_auction(AuctionKind::Opening);
+
+        // crossing orders rest without matching
+        let mut bid = new_order(161, Side::Bid, 30, 102, 161, 1);
+        let result = book.match_bid(&mut bid).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert_eq!(result.remaining_qty, 30);
+        book.match_bid(&mut new_order(162, Side::Bid, 20, 100, 162, 1)).unwrap();
+        book.match_ask(&mut new_order(163, Side::Ask, 25, 99, 163, 1)).unwrap();
+        book.match_ask(&mut new_order(164, Side::Ask, 25, 101, 164, 1)).unwrap();
+        assert_eq!(book.get_best_bid(), Some(102));
+        assert_eq!(book.get_best_ask(), Some(99));
+
+        // ioc and market orders cannot wait for the uncross
+        let mut ioc = new_order(165, Side::Bid, 10, 105, 165, 1);
+        ioc.time_in_force = TimeInForce::Ioc;
+        assert_eq!(book.match_bid(&mut ioc).unwrap().cancelled_qty, 10);
+        assert_eq!(book.match_market_order(&mut Order::market(166, Side::Ask, 10, 166, 1)).unwrap().cancelled_qty, 10);
+
+        // at 101 buyers have 30 and sellers 50 , at 100 buyers 50 and sellers 25
+        let indication = book.indicative_uncross(None).unwrap();
+        assert_eq!((indication.price, indication.matched_qty, indication.imbalance_qty), (101, 30, 20));
+
+        let result = book.uncross(None);
+        assert_eq!(result.kind, AuctionKind::Opening);
+        assert!(result.fills.fills.iter().all(|fill| fill.price == 101));
+        assert_eq!(result.fills.fills.iter().map(|fill| fill.quantity).sum::<u32>(), 30);
+        assert_eq!(book.auction, None);
+        assert_eq!(book.get_last_trade_price(), Some(101));
+        // what did not cross rests for continuous trading
+        assert_eq!(book.get_best_bid(), Some(100));
+        assert_eq!(book.get_best_ask(), Some(101));
+        assert_eq!(book.askside.levels.get(&101).unwrap().get_total_volume(), 20);
+    }
+
+    #[test]
+    fn test_session_phases_gate_the_book() {
+        let mut book = OrderBook::new(1);
+        book.insert_order(new_order(171, Side::Ask, 10, 100, 171, 1));
+        book.insert_order(new_order(172, Side::Ask, 10, 101, 172, 1));
+
+        book.transition(SessionState::Halted).unwrap();
+        // no new orders or amends while halted , cancels still go through
+        let mut bid = new_order(173, Side::Bid, 10, 100, 173, 1);
+        assert!(matches!(book.match_bid(&mut bid), Err(OrderBookError::ActionNotAllowed { state: SessionState::Halted, .. })));
+        assert!(book.amend_order(171, 100, 5).is_err());
+        assert!(book.cancel_order(172).is_some());
+        assert_eq!(book.askside.levels.get(&100).unwrap().get_total_volume(), 10);
+
+        // halted cannot go straight to pre close
+        assert!(matches!(book.transition(SessionState::PreClose), Err(OrderBookError::IllegalTransition { .. })));
+
+        // reopen through an auction , the crossing bid waits for the uncross
+        book.transition(SessionState::PreOpen).unwrap();
+        let result = book.match_bid(&mut new_order(174, Side::Bid, 15, 101, 174, 1)).unwrap();
+        assert!(result.fills.fills.is_empty());
+        let uncross = book.transition(SessionState::Continuous).unwrap().unwrap();
+        assert_eq!(uncross.fills.fills.len(), 1);
+        assert_eq!(uncross.fills.fills[0].quantity, 10);
+        assert_eq!(book.session, SessionState::Continuous);
+        assert_eq!(book.get_best_bid(), Some(101));
+
+        book.transition(SessionState::Closed).unwrap();
+        assert!(book.match_ask(&mut new_order(175, Side::Ask, 5, 101, 175, 1)).is_err());
+    }
+
+    #[test]
+    fn test_static_price_band_rejects_orders() {
+        let mut book = OrderBook::new(1);
+        book.bands = PriceBands { static_bps: 500, static_reference: Some(100), ..Default::default() };
+        book.insert_order(new_order(181, Side::Ask, 10, 104, 181, 1));
+
+        let mut bid = new_order(182, Side::Bid, 10, 106, 182, 1);
+        assert!(matches!(book.match_bid(&mut bid), Err(OrderBookError::PriceOutsideBand { price: 106, low: 95, high: 105 })));
+        assert!(book.match_bid(&mut new_order(183, Side::Bid, 10, 95, 183, 1)).is_ok());
+
+        // an amend out of the band leaves the original order alone
+        assert!(book.amend_order(181, 120, 10).is_err());
+        assert_eq!(book.get_best_ask(), Some(104));
+
+        // so does a size increase at a price the band has since moved away from
+        book.bands.static_reference = Some(90);
+        assert!(matches!(book.amend_order(181, 104, 20), Err(OrderBookError::PriceOutsideBand { price: 104, .. })));
+        assert_eq!(book.askside.levels.get(&104).unwrap().get_total_volume(), 10);
+    }
+
+    #[test]
+    fn test_dynamic_band_starts_volatility_auction() {
+        let mut book = OrderBook::new(1);
+        book.bands = PriceBands { dynamic_bps: 500, volatility_auction_period: 1_000, ..Default::default() };
+        book.insert_order(new_order(191, Side::Ask, 10, 100, 191, 1));
+        book.insert_order(new_order(192, Side::Ask, 10, 104, 192, 1));
+        book.insert_order(new_order(193, Side::Ask, 10, 120, 193, 1));
+        book.match_bid(&mut new_order(194, Side::Bid, 5, 100, 194, 1)).unwrap();
+
+        // the market order walks to 104 , 120 is outside 95..105 and stops it . the auction period runs from
+        // the book's clock , not the client's timestamp
+        assert!(book.on_time(2_000).is_none());
+        let mut market = Order::market(195, Side::Bid, 40, u64::MAX, 1);
+        let result = book.match_market_order(&mut market).unwrap();
+        assert_eq!(result.fills.fills.len(), 2);
+        assert_eq!(result.cancelled_qty, 25);
+        let interruption = result.volatility_interruption.unwrap();
+        assert_eq!(interruption.trigger_price, 120);
+        assert_eq!(interruption.reference_price, 100);
+        assert_eq!(interruption.ends_at, 3_000);
+        assert_eq!(book.auction, Some(AuctionKind::Volatility));
+
+        // orders collect , nothing trades until the period is over
+        let result = book.match_bid(&mut new_order(196, Side::Bid, 10, 125, 2_500, 1)).unwrap();
+        assert!(result.fills.fills.is_empty());
+        assert!(book.on_time(2_999).is_none());
+        let uncross = book.on_time(3_000).unwrap();
+        assert_eq!(uncross.kind, AuctionKind::Volatility);
+        assert_eq!(uncross.fills.fills[0].price, 120);
+        assert_eq!(book.auction, None);
+        assert_eq!(book.volatility_auction_end, None);
+    }
+
+    #[test]
+    fn test_instrument_limits() {
+        let mut instrument = Instrument::new(1, "BTCUSD");
+        instrument.tick_size = 5;
+        instrument.lot_size = 10;
+        instrument.min_qty = 10;
+        instrument.max_qty = 1_000;
+        let mut book = OrderBook::with_instrument(instrument);
+        assert_eq!(book.instrument.tick_size, 5);
+
+        assert!(matches!(book.match_bid(&mut new_order(201, Side::Bid, 10, 103, 201, 1)), Err(OrderBookError::OffTick { price: 103, tick_size: 5 })));
+        assert!(matches!(book.match_bid(&mut new_order(202, Side::Bid, 15, 100, 202, 1)), Err(OrderBookError::OddLot { qty: 15, lot_size: 10 })));
+        assert!(matches!(book.match_ask(&mut new_order(203, Side::Ask, 2_000, 100, 203, 1)), Err(OrderBookError::QtyOutOfRange { .. })));
+        assert!(matches!(book.match_market_order(&mut Order::market(204, Side::Bid, 2_000, 204, 1)), Err(OrderBookError::QtyOutOfRange { .. })));
+        assert!(book.add_stop_order(Order::stop(205, Side::Bid, 10, 102, 205, 1)).is_err());
+
+        book.match_bid(&mut new_order(206, Side::Bid, 20, 100, 206, 1)).unwrap();
+        assert!(book.amend_order(206, 101, 20).is_err());
+        assert!(book.amend_order(206, 100, 15).is_err());
+        assert_eq!(book.get_best_bid(), Some(100));
+        assert!(book.amend_order(206, 100, 10).unwrap().0.priority_kept);
+
+        // trading hours go by the book's clock , cancels still go through outside them
+        let hours = |h : u64| h * 3600 * 1_000_000_000;
+        book.instrument.trading_hours = Some(TradingHours { open: 9 * 3600, close: 16 * 3600 });
+        book.on_time(hours(17));
+        let closed = Err(OrderBookError::OutsideTradingHours { open: 9 * 3600, close: 16 * 3600 });
+        assert_eq!(book.match_bid(&mut new_order(207, Side::Bid, 10, 100, hours(10), 1)).map(|_| ()), closed);
+        assert_eq!(book.match_market_order(&mut Order::market(208, Side::Ask, 10, hours(10), 1)).map(|_| ()), closed);
+        assert_eq!(book.add_stop_order(Order::stop(209, Side::Bid, 10, 105, hours(10), 1)), closed);
+        assert_eq!(book.amend_order(206, 100, 20).map(|_| ()), closed);
+        assert!(book.cancel_order(206).is_some());
+        book.on_time(hours(24 + 10));
+        assert!(book.match_bid(&mut new_order(210, Side::Bid, 10, 100, 0, 1)).is_ok());
+    }
+
+    #[test]
+    fn test_entry_point_validation() {
+        let mut book = OrderBook::new(1);
+        assert_eq!(book.match_bid(&mut new_order(211, Side::Bid, 0, 100, 211, 1)).unwrap_err(), OrderBookError::ZeroQuantity);
+        assert_eq!(book.match_ask(&mut new_order(212, Side::Ask, 10, 0, 212, 1)).unwrap_err(), OrderBookError::ZeroPrice);
+        assert_eq!(book.match_market_order(&mut Order::market(213, Side::Bid, 0, 213, 1)).unwrap_err(), OrderBookError::ZeroQuantity);
+        assert_eq!(book.add_stop_order(Order::stop(214, Side::Bid, 10, 0, 214, 1)).unwrap_err(), OrderBookError::ZeroStopPrice);
+
+        // a reused id is refused instead of overwriting the resting order
+        book.match_bid(&mut new_order(215, Side::Bid, 10, 100, 215, 1)).unwrap();
+        assert_eq!(book.match_bid(&mut new_order(215, Side::Bid, 5, 99, 216, 1)).unwrap_err(), OrderBookError::DuplicateOrderId(215));
+        book.add_stop_order(Order::stop(217, Side::Bid, 10, 110, 217, 1)).unwrap();
+        assert_eq!(book.match_ask(&mut new_order(217, Side::Ask, 5, 120, 218, 1)).unwrap_err(), OrderBookError::DuplicateOrderId(217));
+        assert_eq!(book.bidside.levels.get(&100).unwrap().get_total_volume(), 10);
+        assert_eq!(book.get_best_ask(), None);
+
+        assert_eq!(book.amend_order(215, 0, 10).unwrap_err(), OrderBookError::ZeroPrice);
+        assert_eq!(book.amend_order(999, 100, 10).unwrap_err(), OrderBookError::OrderNotFound(999));
+    }
+
+    #[test]
+    fn test_level_updates_track_every_change() {
+        let mut book = OrderBook::new(1);
+        book.match_bid(&mut new_order(221, Side::Bid, 10, 100, 221, 1)).unwrap();
+        book.match_bid(&mut new_order(222, Side::Bid, 5, 100, 222, 1)).unwrap();
+        book.match_bid(&mut new_order(223, Side::Bid, 7, 99, 223, 1)).unwrap();
+        let updates = book.take_level_updates();
+        assert_eq!(updates.iter().map(|u| (u.price , u.quantity , u.order_count)).collect::<Vec<_>>(), vec![(99 , 7 , 1) , (100 , 15 , 2)]);
+        assert!(book.take_level_updates().is_empty());
+
+        // amend down in place , cancel , and a partial fill each show up as the new aggregate
+        book.amend_order(221, 100, 4).unwrap();
+        book.cancel_order(223);
+        book.match_ask(&mut new_order(224, Side::Ask, 6, 100, 224, 1)).unwrap();
+        let updates = book.take_level_updates();
+        assert_eq!(updates.iter().map(|u| (u.side , u.price , u.quantity , u.order_count)).collect::<Vec<_>>(), vec![(Side::Bid , 99 , 0 , 0) , (Side::Bid , 100 , 3 , 1)]);
+    }
+
+    #[test]
+    fn test_cached_bbo() {
+        let mut book = OrderBook::new(1);
+        assert_eq!(book.get_bbo(), Bbo::default());
+        book.match_bid(&mut new_order(231, Side::Bid, 10, 99, 231, 1)).unwrap();
+        book.match_bid(&mut new_order(232, Side::Bid, 5, 99, 232, 1)).unwrap();
+        book.match_ask(&mut new_order(233, Side::Ask, 8, 101, 233, 1)).unwrap();
+        assert_eq!(book.get_bbo(), Bbo { bid_price: Some(99), bid_qty: 15, ask_price: Some(101), ask_qty: 8 });
+        assert!(book.take_bbo_change().is_some());
+        assert!(book.take_bbo_change().is_none());
+
+        // a bid behind the touch leaves the top alone
+        book.match_bid(&mut new_order(234, Side::Bid, 10, 98, 234, 1)).unwrap();
+        assert!(book.take_bbo_change().is_none());
+
+        // a partial fill changes the size , a cancel moves the price
+        book.match_market_order(&mut Order::market(235, Side::Bid, 3, 235, 1)).unwrap();
+        assert_eq!(book.take_bbo_change().unwrap().bbo.ask_qty, 5);
+        book.cancel_order(231);
+        book.cancel_order(232);
+        let bbo = book.take_bbo_change().unwrap().bbo;
+        assert_eq!((bbo.bid_price , bbo.bid_qty), (Some(98), 10));
+        assert_eq!(book.get_best_bid(), Some(98));
+    }
+
     #[test]
     fn test_resting_orders_and_cancellation() { 
         let mut book = OrderBook::new(1);
@@ -92,7 +717,7 @@ mod tests {
         // Cancel order 10
         book.cancel_order(10);
         // Depth at 101 should be gone now
-        assert!(book.bidside.levels.get(&101).is_none() || book.bidside.levels.get(&101).unwrap().get_total_volume() <= 0);
+        assert!(!book.bidside.levels.contains_key(&101) || book.bidside.levels.get(&101).unwrap().get_total_volume() == 0);
     }
 
     #[test]
@@ -108,7 +733,7 @@ mod tests {
         assert_eq!(result.fills.fills[0].quantity, 80); // Filled all of 200
         assert_eq!(result.remaining_qty, 20); // Rested at 200
         // Next bid fills remaining
-        let mut bid2 = new_order(103, Side::Bid, 20, 200, 4, 2);
+        let mut bid2 = new_order(103, Side::Bid, 20, 201, 4, 2);
         let result2 = book.match_bid(&mut bid2).unwrap();
         assert_eq!(result2.fills.fills.len(), 1);
         assert_eq!(result2.fills.fills[0].quantity, 20);
@@ -125,6 +750,7 @@ mod tests {
         // Insert new order and make sure slot was reused after actual cancellation
         book.manager.remove_order(201);
         let idx_reuse = book.manager.insert_order(new_order(202, Side::Bid, 200, 500, 2, 3));
-        assert_eq!(idx_reuse, 0); // Slot reused if capacity=1
+        assert_eq!(book.manager.all_orders.len(), 1); // Slot reused if capacity=1
+        assert_eq!(book.manager.id_to_key[&202], idx_reuse);
     }
 }
@@ -1,5 +1,6 @@
 use std::sync::Arc;
-use crate::orderbook::order::Side;
+use crate::orderbook::order::{Order, Side};
+use crate::orderbook::session::{SessionAction, SessionState};
 pub type OrderId = u64;
 #[derive(Debug)]
 pub struct Fill{
@@ -8,7 +9,9 @@ pub struct Fill{
     // taker orderid -> incoming order tht caused the match 
     pub taker_order_id : OrderId,
     // the order that was on the book that caused the match 
-    pub maker_order_id : OrderId
+    pub maker_order_id : OrderId,
+    // side of the incoming order , None when an auction uncross matched two resting orders 
+    pub aggressor_side : Option<Side>
 }
 
 impl Fill{
@@ -17,8 +20,8 @@ impl Fill{
              price  ,
              quantity , 
              taker_order_id ,
-             maker_order_id
-
+             maker_order_id ,
+             aggressor_side : None
         }
     }
 
@@ -26,7 +29,7 @@ impl Fill{
         self.price * self.quantity as u64
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Fills{
     pub fills : Vec<Fill>
 }
@@ -49,18 +52,227 @@ pub struct MatchResult{
     /// The ID of the incoming order that initiated the match
     pub order_id : OrderId , 
     pub fills : Fills,
+    /// Quantity left resting on the book
     pub remaining_qty : u32,
+    /// Quantity dropped without resting , e.g. the unfilled part of a market order
+    pub cancelled_qty : u32,
+    /// What happened to a post only order , `None` for everything else
+    pub post_only : Option<PostOnlyOutcome>,
+    /// Quantity kept from trading against the same client and the resting orders that were cancelled for it
+    pub self_trade : SelfTradePrevented,
+    /// Set when the order tried to trade outside the dynamic price band and put the book into an auction
+    pub volatility_interruption : Option<VolatilityInterruption>,
 }
 
 impl MatchResult{
     pub fn new(order_id: OrderId, initial_quantity: u32)->Self{
-        Self { order_id , fills: Fills::new(), remaining_qty: initial_quantity }
+        Self { order_id , fills: Fills::new(), remaining_qty: initial_quantity , cancelled_qty : 0 , post_only : None , self_trade : SelfTradePrevented::default() , volatility_interruption : None }
+    }
+    // a fill or kill order that could not be filled in full , nothing was touched 
+    pub fn killed(order : &mut Order)->Self{
+        let cancelled_qty = order.shares_qty;
+        order.shares_qty = 0;
+        Self { order_id : order.order_id , fills: Fills::new(), remaining_qty: 0 , cancelled_qty , post_only : None , self_trade : SelfTradePrevented::default() , volatility_interruption : None }
+    }
+    // a post only order that would have crossed the book 
+    pub fn post_only_rejected(order : &mut Order)->Self{
+        Self { post_only : Some(PostOnlyOutcome::Rejected) , ..Self::killed(order) }
     }
     pub fn add_transaction(&mut self , fill : Fill){
        self.remaining_qty =  self.remaining_qty.saturating_sub(fill.quantity);
        self.fills.add(fill);
     }
 }
+// self trade prevention , what to do when an incoming order would match a resting order of the same client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StpPolicy{
+    // the incoming order loses its remaining quantity 
+    CancelNewest ,
+    // the resting order is cancelled and matching carries on 
+    CancelOldest ,
+    CancelBoth ,
+    // both are reduced by the smaller quantity , whichever reaches zero is cancelled 
+    DecrementAndCancel
+}
+
+#[derive(Debug, Default)]
+pub struct SelfTradePrevented{
+    // quantity that would have traded between the two 
+    pub prevented_qty : u32 ,
+    // quantity taken off the incoming order , also counted in cancelled_qty 
+    pub taker_cancelled_qty : u32 ,
+    pub cancelled_makers : Vec<OrderCancelled> ,
+    // resting orders that stay on the book with less , cancelled_qty is what they lost 
+    pub reduced_makers : Vec<OrderCancelled>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostOnlyOutcome{
+    // did not cross , rested at its own price 
+    Rested ,
+    // would have crossed and was dropped 
+    Rejected ,
+    // would have crossed and was moved one tick behind the touch 
+    Repriced { original_price : u64 , new_price : u64 }
+}
+
+// a stop order left the trigger book , its execution follows as a MatchResult 
+#[derive(Debug)]
+pub struct StopTriggered{
+    pub order_id : OrderId ,
+    pub side : Side ,
+    pub stop_price : u64 ,
+    // the trade price that fired it 
+    pub last_trade_price : u64
+}
+
+// old and new state of an amended order , a reduction at the same price keeps its place in the queue
+// anything else is a cancel replace and the new order goes to the back of its level 
+#[derive(Debug)]
+pub struct OrderAmended{
+    pub order_id : OrderId ,
+    pub side : Side ,
+    pub old_price : u64 ,
+    pub old_qty : u32 ,
+    pub new_price : u64 ,
+    pub new_qty : u32 ,
+    pub priority_kept : bool
+}
+
+// a resting or parked stop order was taken off the book 
+#[derive(Debug)]
+pub struct OrderCancelled{
+    pub order_id : OrderId ,
+    pub side : Side ,
+    pub price : u64 ,
+    pub cancelled_qty : u32
+}
+
+impl OrderCancelled{
+    pub fn new(order : &Order)->Self{
+        Self { order_id : order.order_id , side : order.side , price : order.price , cancelled_qty : order.total_qty() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuctionKind{
+    Opening ,
+    Closing ,
+    // a trade would have printed outside the dynamic band , runs for a fixed period then uncrosses
+    Volatility
+}
+
+// continuous trading stopped because the next trade would have printed at `trigger_price` ,
+// outside the dynamic band [low , high] around `reference_price`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolatilityInterruption{
+    pub trigger_price : u64 ,
+    pub reference_price : u64 ,
+    pub low : u64 ,
+    pub high : u64 ,
+    // timestamp the auction uncrosses at 
+    pub ends_at : u64
+}
+
+// what the auction would do if it uncrossed right now , published while orders accumulate 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuctionIndication{
+    pub price : u64 ,
+    pub matched_qty : u64 ,
+    // volume left on the heavier side at that price 
+    pub imbalance_qty : u64 ,
+    pub imbalance_side : Option<Side>
+}
+
+// the auction call ended , every crossing order traded at the one equilibrium price
+// in the fills the buyer is reported as the taker and the seller as the maker 
+#[derive(Debug)]
+pub struct AuctionResult{
+    pub kind : AuctionKind ,
+    // None when nothing crossed and nothing traded 
+    pub indication : Option<AuctionIndication> ,
+    pub fills : Fills
+}
+
+#[derive(Debug)]
+pub struct SessionChanged{
+    pub symbol : u32 ,
+    pub from : SessionState ,
+    pub to : SessionState
+}
+
+// an order , amend or cancel the book refused , nothing about the book changed 
+#[derive(Debug)]
+pub struct OrderRejected{
+    pub order_id : OrderId ,
+    pub reason_code : u16 ,
+    pub reason : OrderBookError
+}
+
+impl OrderRejected{
+    pub fn new(order_id : OrderId , reason : OrderBookError)->Self{
+        Self { order_id , reason_code : reason.code() , reason }
+    }
+}
+
+// the exchange id the engine gave a client's order , every later event about it uses `order_id` 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderIdAssigned{
+    pub client_id : u32 ,
+    pub client_order_id : OrderId ,
+    pub order_id : OrderId
+}
+
+// what happened to an order , one report per change so a client can rebuild its order state from the stream 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecType{
+    // accepted by the book , resting , parked as a stop or about to trade 
+    New ,
+    PartialFill ,
+    Fill ,
+    // taken off by a cancel , an amend to zero or self trade prevention 
+    Cancelled ,
+    // price or quantity changed by an amend 
+    Replaced ,
+    Rejected ,
+    // the part of an ioc , fok or market order that could not trade 
+    Expired ,
+    // quantity taken off by the engine while the order stays live , self trade prevention 
+    Restated
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity{
+    Maker ,
+    Taker ,
+    // both sides of an uncross trade at the same price , neither took liquidity 
+    Auction
+}
+
+// the state of one order after a lifecycle change , always from that order's own side even for a maker 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionReport{
+    pub order_id : OrderId ,
+    pub client_id : u32 ,
+    pub symbol : u32 ,
+    // None only on a rejection whose side did not decode 
+    pub side : Option<Side> ,
+    pub exec_type : ExecType ,
+    // cum_qty + leaves_qty , what the order is for after any amend 
+    pub order_qty : u32 ,
+    pub cum_qty : u32 ,
+    // still working on the book , 0 once the order is done 
+    pub leaves_qty : u32 ,
+    // volume weighted over every fill so far , 0 before the first one 
+    pub avg_price : f64 ,
+    // the fill this report is for , 0 for anything that is not a fill 
+    pub last_price : u64 ,
+    pub last_qty : u32 ,
+    pub liquidity : Option<Liquidity> ,
+    // set on rejections the book gave a reason for , a post only reject has none 
+    pub reject_reason : Option<OrderBookError>
+}
+
 #[derive(Debug)]
 pub struct TradeResult {
     pub symbol : String ,
@@ -77,11 +289,71 @@ impl TradeResult{
 pub type TradeListener = Arc<dyn Fn(TradeResult) + Send + Sync>;
 
 
-#[derive(Debug)]
+// public time and sales print , one per fill . carries no order or client ids , those stay in the execution reports 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trade{
+    // engine assigned , increases by one per print 
+    pub trade_id : u64 ,
+    pub symbol : u32 ,
+    pub aggressor_side : Option<Side> ,
+    pub price : u64 ,
+    pub quantity : u32 ,
+    // engine clock when the message that caused the trade was taken , its received_at 
+    pub timestamp : u64
+}
+
+// best price and the displayed quantity resting there on each side , None and 0 for an empty side 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bbo{
+    pub bid_price : Option<u64> ,
+    pub bid_qty : u32 ,
+    pub ask_price : Option<u64> ,
+    pub ask_qty : u32
+}
+
+// the top of the book moved , sent at most once per incoming message 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BboChanged{
+    pub symbol : u32 ,
+    pub bbo : Bbo
+}
+
+// open high low close volume over one time bucket , buckets are aligned to the unix epoch 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bar{
+    pub symbol : u32 ,
+    // bar length in nanoseconds 
+    pub interval : u64 ,
+    // first nanosecond of the bar , it covers [start , start + interval) 
+    pub start : u64 ,
+    pub open : u64 ,
+    pub high : u64 ,
+    pub low : u64 ,
+    pub close : u64 ,
+    pub volume : u64 ,
+    // sum of price * quantity 
+    pub turnover : u128 ,
+    pub trade_count : u64
+}
+
+impl Bar{
+    pub fn end(&self)->u64{
+        self.start + self.interval
+    }
+
+    pub fn vwap(&self)->f64{
+        self.turnover as f64 / self.volume as f64
+    }
+}
+
+// incremental L2 , the displayed quantity and order count now resting at a price , 0 means the level is gone 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PriceLevelChangedEvent{
+    pub symbol : u32 ,
     pub side : Side  ,
     pub quantity : u64 , 
     pub price : u64,
+    pub order_count : u32
 }
 
 pub type PriceLevelChangedEventListener = Arc<dyn Fn(PriceLevelChangedEvent) + Send+Sync>;
@@ -89,14 +361,119 @@ pub type PriceLevelChangedEventListener = Arc<dyn Fn(PriceLevelChangedEvent) + S
 // dyn Fn() means any type which taken in a PricelevelChangedEvent and returns nothing 
 // if we dint want it to be thread safe we cud have used just Box 
 
-#[derive(Debug)]
+// why the book refused an order , amend , cancel or session change . nothing about the book changed 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OrderBookError{
     // aff errors that can occour 
+    OrderNotFound(OrderId),
+    // an order with this id is already resting or parked as a stop 
+    DuplicateOrderId(OrderId),
+    ZeroQuantity,
+    // a limit order or the limit of a stop limit without a price 
+    ZeroPrice,
+    // a stop order without a stop price 
+    ZeroStopPrice,
+    // a field on the wire that does not decode , `field` names it 
+    InvalidField { field : &'static str , value : u8 },
+    // no book trades this symbol 
+    UnknownSymbol(u32),
+    // the book's trading phase does not allow this right now 
+    ActionNotAllowed { state : SessionState , action : SessionAction },
+    IllegalTransition { from : SessionState , to : SessionState },
+    // limit price outside the static band [low , high] 
+    PriceOutsideBand { price : u64 , low : u64 , high : u64 },
+    // the instrument's reference data does not allow this price or quantity 
+    OffTick { price : u64 , tick_size : u64 },
+    OddLot { qty : u32 , lot_size : u32 },
+    QtyOutOfRange { qty : u32 , min_qty : u32 , max_qty : u32 },
+    // the instrument does not trade at this time of day , open and close are seconds after midnight utc 
+    OutsideTradingHours { open : u32 , close : u32 },
+    // the order is a parked stop or stop limit , those are cancelled and sent again rather than amended 
+    StopNotAmendable(OrderId)
+}
+
+impl OrderBookError{
+    // stable number for the wire , never reuse one 
+    pub fn code(&self)->u16{
+        match self{
+            OrderBookError::OrderNotFound(_) => 1,
+            OrderBookError::DuplicateOrderId(_) => 2,
+            OrderBookError::ZeroQuantity => 3,
+            OrderBookError::ZeroPrice => 4,
+            OrderBookError::ZeroStopPrice => 5,
+            OrderBookError::InvalidField { .. } => 6,
+            OrderBookError::UnknownSymbol(_) => 7,
+            OrderBookError::ActionNotAllowed { .. } => 8,
+            OrderBookError::IllegalTransition { .. } => 9,
+            OrderBookError::PriceOutsideBand { .. } => 10,
+            OrderBookError::OffTick { .. } => 11,
+            OrderBookError::OddLot { .. } => 12,
+            OrderBookError::QtyOutOfRange { .. } => 13,
+            OrderBookError::OutsideTradingHours { .. } => 14,
+            OrderBookError::StopNotAmendable(_) => 15,
+        }
+    }
 }
+
+impl std::fmt::Display for OrderBookError{
+    fn fmt(&self , f : &mut std::fmt::Formatter<'_>)->std::fmt::Result{
+        match self{
+            OrderBookError::OrderNotFound(order_id) => write!(f , "order {} not found" , order_id),
+            OrderBookError::DuplicateOrderId(order_id) => write!(f , "order id {} is already in use" , order_id),
+            OrderBookError::ZeroQuantity => write!(f , "quantity must be positive"),
+            OrderBookError::ZeroPrice => write!(f , "price must be positive"),
+            OrderBookError::ZeroStopPrice => write!(f , "stop price must be positive"),
+            OrderBookError::InvalidField { field , value } => write!(f , "invalid {} {}" , field , value),
+            OrderBookError::UnknownSymbol(symbol) => write!(f , "unknown symbol {}" , symbol),
+            OrderBookError::ActionNotAllowed { state , action } => write!(f , "{:?} not allowed while {:?}" , action , state),
+            OrderBookError::IllegalTransition { from , to } => write!(f , "cannot go from {:?} to {:?}" , from , to),
+            OrderBookError::PriceOutsideBand { price , low , high } => write!(f , "price {} outside band {}..={}" , price , low , high),
+            OrderBookError::OffTick { price , tick_size } => write!(f , "price {} is not a multiple of tick size {}" , price , tick_size),
+            OrderBookError::OddLot { qty , lot_size } => write!(f , "quantity {} is not a multiple of lot size {}" , qty , lot_size),
+            OrderBookError::QtyOutOfRange { qty , min_qty , max_qty } => write!(f , "quantity {} outside {}..={}" , qty , min_qty , max_qty),
+            OrderBookError::OutsideTradingHours { open , close } => write!(f , "outside trading hours {:02}:{:02}-{:02}:{:02}" , open / 3600 , open % 3600 / 60 , close / 3600 , close % 3600 / 60),
+            OrderBookError::StopNotAmendable(order_id) => write!(f , "order {} is a parked stop and cannot be amended" , order_id),
+        }
+    }
+}
+
+impl std::error::Error for OrderBookError{}
+
+// what goes out on the channel , every event stamped with where it sits in the engine's output
+// and which input caused it 
+#[derive(Debug)]
+pub struct SequencedEvent{
+    pub engine_id : usize ,
+    // engine wide , starts at 1 and has no gaps 
+    pub seq : u64 ,
+    pub symbol : u32 ,
+    // per symbol , starts at 1 and has no gaps 
+    pub symbol_seq : u64 ,
+    // position in the input queue of the message that caused the event 
+    pub input_position : u64 ,
+    // nanoseconds since the unix epoch when the engine took the message , and when it sent this event 
+    pub received_at : u64 ,
+    pub emitted_at : u64 ,
+    pub event : Event
+}
+
 #[derive(Debug)]
 pub enum Event {
     PriceLevelChangedEvent(PriceLevelChangedEvent) ,
-    MatchResult(MatchResult)
+    MatchResult(MatchResult) ,
+    StopTriggered(StopTriggered) ,
+    OrderAmended(OrderAmended) ,
+    OrderCancelled(OrderCancelled) ,
+    AuctionIndication(AuctionIndication) ,
+    AuctionUncrossed(AuctionResult) ,
+    SessionChanged(SessionChanged) ,
+    VolatilityInterruption(VolatilityInterruption) ,
+    OrderRejected(OrderRejected) ,
+    OrderIdAssigned(OrderIdAssigned) ,
+    ExecutionReport(ExecutionReport) ,
+    BboChanged(BboChanged) ,
+    Trade(Trade) ,
+    BarClosed(Bar)
 }
 pub struct PubLishError{
 
@@ -1,17 +1,170 @@
-use crossbeam::channel::Receiver;
-use crate::orderbook::types::Event;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use crate::orderbook::types::SequencedEvent;
+use crate::publisher::sink::EventSink;
+
+// one drained batch , shared by every sink 
+pub type Batch = Arc<Vec<SequencedEvent>>;
+
+// what happens to a batch when a sink's queue is full 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // the sink misses the batch , the engine channel keeps draining 
+    Drop,
+    // wait for the sink , every other sink and the engine channel wait with it 
+    Block,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SinkConfig {
+    // batches that can wait for the sink 
+    pub queue_batches: usize,
+    pub overflow: Overflow,
+    // failed batches in a row after which the sink is shut down , None keeps trying forever 
+    pub give_up_after: Option<u32>,
+}
+
+impl Default for SinkConfig {
+    fn default() -> Self {
+        Self { queue_batches: 1024, overflow: Overflow::Drop, give_up_after: None }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SinkStats {
+    pub batches: AtomicU64,
+    pub events: AtomicU64,
+    pub dropped_batches: AtomicU64,
+    pub failed_batches: AtomicU64,
+}
+
+struct SinkHandle {
+    name: String,
+    config: SinkConfig,
+    // None once the sink gave up 
+    sender: Option<Sender<Batch>>,
+    stats: Arc<SinkStats>,
+    worker: Option<JoinHandle<()>>,
+}
+
+// runs on the sink's own thread until the publisher hangs up or the sink gives up 
+fn run_sink(mut sink: Box<dyn EventSink>, batches: Receiver<Batch>, stats: Arc<SinkStats>, give_up_after: Option<u32>) {
+    let mut failures_in_row = 0u32;
+    for batch in batches {
+        match sink.publish(&batch) {
+            Ok(()) => {
+                if failures_in_row > 0 {
+                    eprintln!("[PUBLISHER] sink {} recovered after {} failed batches", sink.name(), failures_in_row);
+                }
+                failures_in_row = 0;
+                stats.batches.fetch_add(1, Ordering::Relaxed);
+                stats.events.fetch_add(batch.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                // only the first failure of a run is logged 
+                if failures_in_row == 0 {
+                    eprintln!("[PUBLISHER] sink {} failed: {}", sink.name(), e);
+                }
+                failures_in_row += 1;
+                stats.failed_batches.fetch_add(1, Ordering::Relaxed);
+                if give_up_after.is_some_and(|limit| failures_in_row >= limit) {
+                    eprintln!("[PUBLISHER] sink {} gave up after {} failed batches", sink.name(), failures_in_row);
+                    return;
+                }
+            }
+        }
+    }
+    if let Err(e) = sink.flush() {
+        eprintln!("[PUBLISHER] sink {} failed to flush: {}", sink.name(), e);
+    }
+}
 
 pub struct EventPublisher {
-    receiver: Receiver<Event>,
+    receiver: Receiver<SequencedEvent>,
+    sinks: Vec<SinkHandle>,
+    // batches handed to the sinks , oldest first . a buffer is taken back once every sink let go of it 
+    in_flight: VecDeque<Batch>,
+    // most events taken off the engine channel at once 
+    pub max_batch: usize,
 }
 
 impl EventPublisher {
-    pub fn new(rx: Receiver<Event>) -> Self {
-        Self { receiver: rx }
+    pub fn new(rx: Receiver<SequencedEvent>) -> Self {
+        Self { receiver: rx, sinks: Vec::new(), in_flight: VecDeque::new(), max_batch: 10_000 }
+    }
+
+    // starts a thread for the sink , the returned stats are updated as it works 
+    pub fn add_sink(&mut self, sink: Box<dyn EventSink>, config: SinkConfig) -> Arc<SinkStats> {
+        let (sender, batches) = crossbeam::channel::bounded(config.queue_batches);
+        let stats = Arc::new(SinkStats::default());
+        let name = sink.name().to_string();
+        let worker_stats = stats.clone();
+        let worker = std::thread::Builder::new()
+            .name(format!("sink {}", name))
+            .spawn(move || run_sink(sink, batches, worker_stats, config.give_up_after))
+            .expect("Failed to spawn sink thread");
+        self.sinks.push(SinkHandle { name, config, sender: Some(sender), stats: stats.clone(), worker: Some(worker) });
+        stats
+    }
+
+    // the oldest handed out buffer if no sink holds it any more , a new one otherwise 
+    fn spare_buffer(&mut self) -> Vec<SequencedEvent> {
+        if let Some(oldest) = self.in_flight.front_mut()
+            && let Some(buffer) = Arc::get_mut(oldest) {
+                let mut buffer = std::mem::take(buffer);
+                self.in_flight.pop_front();
+                buffer.clear();
+                return buffer;
+        }
+        Vec::with_capacity(self.max_batch)
+    }
+
+    // hands the batch to every sink still running , never waits on a sink set to drop .
+    // `batch` is swapped for a recycled buffer so the next drain does not allocate 
+    fn dispatch(&mut self, batch: &mut Vec<SequencedEvent>) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let spare = self.spare_buffer();
+        let batch: Batch = Arc::new(std::mem::replace(batch, spare));
+        for sink in &mut self.sinks {
+            let Some(sender) = &sink.sender else { continue };
+            let disconnected = match sink.config.overflow {
+                Overflow::Drop => match sender.try_send(batch.clone()) {
+                    Ok(()) => false,
+                    Err(TrySendError::Full(_)) => {
+                        sink.stats.dropped_batches.fetch_add(1, Ordering::Relaxed);
+                        false
+                    }
+                    Err(TrySendError::Disconnected(_)) => true,
+                },
+                Overflow::Block => sender.send(batch.clone()).is_err(),
+            };
+            // the worker returned , it gave up 
+            if disconnected {
+                sink.sender = None;
+            }
+        }
+        self.in_flight.push_back(batch);
+    }
+
+    // lets every sink finish what is queued and waits for it 
+    fn shutdown(&mut self) {
+        for sink in &mut self.sinks {
+            sink.sender = None;
+        }
+        for sink in &mut self.sinks {
+            if let Some(worker) = sink.worker.take() && worker.join().is_err() {
+                eprintln!("[PUBLISHER] sink {} panicked", sink.name);
+            }
+        }
     }
 
     pub fn start_publisher(&mut self) {
-        let mut batch = Vec::with_capacity(10_000);
+        let mut batch = Vec::with_capacity(self.max_batch);
         let mut count = 0u64;
         let mut total_batches = 0u64;
         let mut last_log = std::time::Instant::now();
@@ -29,21 +182,21 @@ impl EventPublisher {
                 }
                 Err(_) => {
                     println!("[PUBLISHER] Channel closed, exiting");
+                    self.shutdown();
                     break;
                 }
             }
             
-            //  Non-blocking drain (get up to 9,999 more)
-            for event in self.receiver.try_iter().take(9_999) {
+            //  Non-blocking drain (up to max_batch in all)
+            for event in self.receiver.try_iter().take(self.max_batch.saturating_sub(1)) {
                 batch.push(event);
                 count += 1;
             }
             
             total_batches += 1;
             
-            // Step 3: Process batch (currently just drops)
-            // TODO: When publishing to Kafka:
-            // publish_batch_to_kafka(&batch);
+            // Step 3: fan out to the sinks , with none configured the batch is dropped 
+            self.dispatch(&mut batch);
             
             // Step 4: Stats every 5 seconds
             if last_log.elapsed().as_secs() >= 5 {
@@ -56,6 +209,14 @@ impl EventPublisher {
                 
                 eprintln!("[PUBLISHER] {:.2}M events/sec, avg batch: {:.0}",
                     rate / 1_000_000.0, avg_batch_size);
+                for sink in &self.sinks {
+                    eprintln!("[PUBLISHER] sink {}: {} events, {} dropped batches, {} failed batches{}",
+                        sink.name,
+                        sink.stats.events.load(Ordering::Relaxed),
+                        sink.stats.dropped_batches.load(Ordering::Relaxed),
+                        sink.stats.failed_batches.load(Ordering::Relaxed),
+                        if sink.sender.is_none() { " (stopped)" } else { "" });
+                }
                 
                 count = 0;
                 total_batches = 0;
@@ -64,3 +225,133 @@ impl EventPublisher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::Mutex;
+    use crate::orderbook::types::{Event, OrderRejected, OrderBookError};
+
+    fn sequenced(seq: u64) -> SequencedEvent {
+        SequencedEvent {
+            engine_id: 0,
+            seq,
+            symbol: 0,
+            symbol_seq: seq,
+            input_position: seq,
+            received_at: 0,
+            emitted_at: 0,
+            event: Event::OrderRejected(OrderRejected::new(seq, OrderBookError::ZeroQuantity)),
+        }
+    }
+
+    // keeps the sequence numbers it saw , fails every batch holding `fail_on` 
+    struct MemorySink {
+        seen: Arc<Mutex<Vec<u64>>>,
+        fail_on: Option<u64>,
+    }
+
+    impl EventSink for MemorySink {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+            if batch.iter().any(|event| Some(event.seq) == self.fail_on) {
+                return Err(io::Error::other("refused"));
+            }
+            self.seen.lock().unwrap().extend(batch.iter().map(|event| event.seq));
+            Ok(())
+        }
+    }
+
+    // parks in its first batch until the test lets go 
+    struct StuckSink {
+        started: Sender<()>,
+        release: Receiver<()>,
+    }
+
+    impl EventSink for StuckSink {
+        fn name(&self) -> &str {
+            "stuck"
+        }
+
+        fn publish(&mut self, _batch: &[SequencedEvent]) -> io::Result<()> {
+            let _ = self.started.send(());
+            let _ = self.release.recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_batch_buffers_are_recycled() {
+        let (_tx, rx) = crossbeam::channel::unbounded();
+        let mut publisher = EventPublisher::new(rx);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        publisher.add_sink(Box::new(MemorySink { seen: seen.clone(), fail_on: None }), SinkConfig::default());
+        let mut batch = Vec::with_capacity(16);
+        batch.push(sequenced(1));
+        let first = batch.as_ptr();
+        publisher.dispatch(&mut batch);
+        // wait for the sink to let go of it
+        while Arc::strong_count(&publisher.in_flight[0]) > 1 {
+            std::thread::yield_now();
+        }
+        batch.push(sequenced(2));
+        publisher.dispatch(&mut batch);
+        assert!(batch.is_empty());
+        assert_eq!(batch.as_ptr(), first);
+        publisher.shutdown();
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_fan_out_and_failing_sink() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut publisher = EventPublisher::new(rx);
+        publisher.max_batch = 1;
+        let good = Arc::new(Mutex::new(Vec::new()));
+        let flaky = Arc::new(Mutex::new(Vec::new()));
+        let quitter = Arc::new(Mutex::new(Vec::new()));
+        let block = SinkConfig { overflow: Overflow::Block, ..SinkConfig::default() };
+        publisher.add_sink(Box::new(MemorySink { seen: good.clone(), fail_on: None }), block);
+        let flaky_stats = publisher.add_sink(Box::new(MemorySink { seen: flaky.clone(), fail_on: Some(2) }), block);
+        let quitter_stats = publisher.add_sink(Box::new(MemorySink { seen: quitter.clone(), fail_on: Some(1) }),
+            SinkConfig { give_up_after: Some(1), ..block });
+        for seq in 1..=4 {
+            tx.send(sequenced(seq)).unwrap();
+        }
+        drop(tx);
+        publisher.start_publisher();
+
+        assert_eq!(*good.lock().unwrap(), vec![1, 2, 3, 4]);
+        // one failed batch does not stop the sink or the others 
+        assert_eq!(*flaky.lock().unwrap(), vec![1, 3, 4]);
+        assert_eq!(flaky_stats.failed_batches.load(Ordering::Relaxed), 1);
+        assert_eq!(flaky_stats.events.load(Ordering::Relaxed), 3);
+        assert!(quitter.lock().unwrap().is_empty());
+        assert_eq!(quitter_stats.failed_batches.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_slow_sink_drops_instead_of_blocking() {
+        let (_tx, rx) = crossbeam::channel::unbounded();
+        let mut publisher = EventPublisher::new(rx);
+        let (started_tx, started) = crossbeam::channel::unbounded();
+        let (release_tx, release) = crossbeam::channel::unbounded();
+        let stats = publisher.add_sink(Box::new(StuckSink { started: started_tx, release }),
+            SinkConfig { queue_batches: 1, ..SinkConfig::default() });
+
+        publisher.dispatch(&mut vec![sequenced(1)]);
+        started.recv().unwrap();
+        // one batch waits in the queue , the next two are dropped 
+        for seq in 2..=4 {
+            publisher.dispatch(&mut vec![sequenced(seq)]);
+        }
+        assert_eq!(stats.dropped_batches.load(Ordering::Relaxed), 2);
+        drop(release_tx);
+        publisher.shutdown();
+        assert_eq!(stats.batches.load(Ordering::Relaxed), 2);
+    }
+}
@@ -1 +1,2 @@
-pub mod event_publisher;
\ No newline at end of file
+pub mod event_publisher;
+pub mod sink;
@@ -0,0 +1,320 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket, SocketAddr};
+use std::path::Path;
+use crate::orderbook::types::SequencedEvent;
+use crate::shm::event_ring::EventRing;
+use crate::journal::event_journal::EventJournalSink;
+use crate::journal::segment::JournalConfig;
+use crate::journal::event_codec::encode_event;
+
+// somewhere published events go . each sink gets every batch the publisher drains , on its own thread ,
+// an error only affects that sink
+pub trait EventSink: Send {
+    fn name(&self) -> &str;
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    // highest seq the sink already holds from an earlier run , the engine has to carry on after it 
+    fn last_seq(&self) -> Option<u64> {
+        None
+    }
+}
+
+// one text line per event , the envelope first and the event as its debug form
+pub fn encode_line(event: &SequencedEvent, out: &mut Vec<u8>) {
+    let _ = writeln!(
+        out,
+        "{} {} {} {} {} {:?}",
+        event.engine_id, event.seq, event.symbol, event.symbol_seq, event.input_position, event.event
+    );
+}
+
+// lines to anything that can be written to , stdout or an append only file
+pub struct WriterSink<W: Write + Send> {
+    name: String,
+    writer: W,
+    line: Vec<u8>,
+}
+
+impl<W: Write + Send> WriterSink<W> {
+    pub fn new(name: &str, writer: W) -> Self {
+        Self { name: name.to_string(), writer, line: Vec::with_capacity(256) }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl WriterSink<io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new("stdout", io::stdout())
+    }
+}
+
+impl WriterSink<BufWriter<File>> {
+    // appends to `path` , creating it if needed
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path.as_ref())?;
+        Ok(Self::new(&path.as_ref().display().to_string(), BufWriter::new(file)))
+    }
+}
+
+impl<W: Write + Send> EventSink for WriterSink<W> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        for event in batch {
+            self.line.clear();
+            encode_line(event, &mut self.line);
+            self.writer.write_all(&self.line)?;
+        }
+        self.writer.flush()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// through the log crate , formats nothing unless the level is enabled
+pub struct LogSink {
+    pub level: log::Level,
+}
+
+impl EventSink for LogSink {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        if !log::log_enabled!(self.level) {
+            return Ok(());
+        }
+        for event in batch {
+            log::log!(self.level, "seq {} symbol {} {:?}", event.seq, event.symbol, event.event);
+        }
+        Ok(())
+    }
+}
+
+// lines over a tcp connection . a broken connection is dropped and dialled again on the next batch ,
+// the batch that failed is lost for this sink
+pub struct TcpSink {
+    name: String,
+    addr: SocketAddr,
+    stream: Option<BufWriter<TcpStream>>,
+    line: Vec<u8>,
+}
+
+impl TcpSink {
+    pub fn new<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))?;
+        Ok(Self { name: format!("tcp {}", addr), addr, stream: None, line: Vec::with_capacity(256) })
+    }
+
+    fn stream(&mut self) -> io::Result<&mut BufWriter<TcpStream>> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect(self.addr)?;
+            stream.set_nodelay(true)?;
+            self.stream = Some(BufWriter::new(stream));
+        }
+        Ok(self.stream.as_mut().expect("connected above"))
+    }
+
+    fn write_batch(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        let mut line = std::mem::take(&mut self.line);
+        let stream = self.stream()?;
+        let written = batch.iter().try_for_each(|event| {
+            line.clear();
+            encode_line(event, &mut line);
+            stream.write_all(&line)
+        });
+        self.line = line;
+        written?;
+        self.stream()?.flush()
+    }
+}
+
+impl EventSink for TcpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        let result = self.write_batch(batch);
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+}
+
+// one datagram per event , nothing is resent
+pub struct UdpSink {
+    name: String,
+    socket: UdpSocket,
+    line: Vec<u8>,
+}
+
+impl UdpSink {
+    pub fn new<A: ToSocketAddrs>(target: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(target)?;
+        Ok(Self { name: format!("udp {}", socket.peer_addr()?), socket, line: Vec::with_capacity(256) })
+    }
+}
+
+impl EventSink for UdpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        for event in batch {
+            self.line.clear();
+            encode_line(event, &mut self.line);
+            self.socket.send(&self.line)?;
+        }
+        Ok(())
+    }
+}
+
+// one ring record per event for readers on the same host , in the binary encoding the event journal uses
+// so a reader decodes it with decode_event
+pub struct ShmRingSink {
+    ring: EventRing,
+    record: Vec<u8>,
+}
+
+impl ShmRingSink {
+    pub fn new(ring: EventRing) -> Self {
+        Self { ring, record: Vec::with_capacity(256) }
+    }
+}
+
+impl EventSink for ShmRingSink {
+    fn name(&self) -> &str {
+        "shm ring"
+    }
+
+    fn publish(&mut self, batch: &[SequencedEvent]) -> io::Result<()> {
+        for event in batch {
+            self.record.clear();
+            encode_event(event, &mut self.record);
+            self.ring.push(&self.record).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.ring.flush().map_err(io::Error::other)
+    }
+}
+
+// default size of the data area of a ring made from a spec
+pub const RING_BYTES: u32 = 64 << 20;
+
+// builds a sink from `kind` or `kind:target` , one of
+// stdout , log , file:<path> , tcp:<host:port> , udp:<host:port> , shm:<path> , journal:<dir>
+pub fn from_spec(spec: &str) -> io::Result<Box<dyn EventSink>> {
+    let (kind, target) = spec.split_once(':').unwrap_or((spec, ""));
+    let sink: Box<dyn EventSink> = match (kind, target) {
+        ("stdout", "") => Box::new(WriterSink::stdout()),
+        ("log", "") => Box::new(LogSink { level: log::Level::Info }),
+        ("file", path) if !path.is_empty() => Box::new(WriterSink::file(path)?),
+        ("tcp", addr) if !addr.is_empty() => Box::new(TcpSink::new(addr)?),
+        ("udp", addr) if !addr.is_empty() => Box::new(UdpSink::new(addr)?),
+        ("shm", path) if !path.is_empty() => {
+            Box::new(ShmRingSink::new(EventRing::create(path, RING_BYTES).map_err(io::Error::other)?))
+        }
+        ("journal", dir) if !dir.is_empty() => Box::new(EventJournalSink::open(dir, JournalConfig::default())?),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown sink {}", spec))),
+    };
+    Ok(sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use crate::orderbook::types::{Event, OrderRejected, OrderBookError};
+    use crate::shm::event_ring::{RingRead, RingReader};
+    use crate::journal::event_codec::decode_event;
+
+    fn sequenced(seq: u64) -> SequencedEvent {
+        SequencedEvent {
+            engine_id: 0,
+            seq,
+            symbol: 3,
+            symbol_seq: seq,
+            input_position: seq - 1,
+            received_at: 0,
+            emitted_at: 0,
+            event: Event::OrderRejected(OrderRejected::new(seq, OrderBookError::ZeroQuantity)),
+        }
+    }
+
+    #[test]
+    fn test_writer_sink_lines() {
+        let mut sink = WriterSink::new("memory", Vec::new());
+        sink.publish(&[sequenced(1), sequenced(2)]).unwrap();
+        let text = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("0 2 3 2 1 OrderRejected"));
+    }
+
+    #[test]
+    fn test_sink_specs() {
+        assert_eq!(from_spec("stdout").unwrap().name(), "stdout");
+        assert_eq!(from_spec("udp:127.0.0.1:9").unwrap().name(), "udp 127.0.0.1:9");
+        assert!(from_spec("file:").is_err());
+        assert!(from_spec("kafka:broker:9092").is_err());
+    }
+
+    #[test]
+    fn test_tcp_and_udp_sinks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut tcp = TcpSink::new(listener.local_addr().unwrap()).unwrap();
+        tcp.publish(&[sequenced(1)]).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert!(line.starts_with("0 1 3 1 0 "));
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut udp = UdpSink::new(receiver.local_addr().unwrap()).unwrap();
+        udp.publish(&[sequenced(5)]).unwrap();
+        let mut datagram = [0u8; 512];
+        let len = receiver.recv(&mut datagram).unwrap();
+        assert!(datagram[..len].starts_with(b"0 5 3 5 4 "));
+    }
+
+    #[test]
+    fn test_shm_ring_sink() {
+        let path = std::env::temp_dir().join(format!("test_shm_ring_sink_{}", std::process::id()));
+        let mut sink = ShmRingSink::new(EventRing::create(&path, 4096).unwrap());
+        sink.publish(&[sequenced(1), sequenced(2)]).unwrap();
+        let reader = RingReader::open(&path).unwrap();
+        let mut out = Vec::new();
+        let RingRead::Record { next } = reader.read(0, &mut out) else { panic!("first record missing") };
+        let event = decode_event(&out).unwrap();
+        assert_eq!((event.seq, event.symbol, event.input_position), (1, 3, 0));
+        assert!(matches!(event.event, Event::OrderRejected(OrderRejected { order_id: 1, .. })));
+        assert!(matches!(reader.read(next, &mut out), RingRead::Record { .. }));
+        assert_eq!(decode_event(&out).unwrap().seq, 2);
+        let _ = std::fs::remove_file(path);
+    }
+}
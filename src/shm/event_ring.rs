@@ -0,0 +1,278 @@
+use memmap2::{Mmap, MmapMut};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+// broadcast ring of length prefixed records for readers in other processes
+// the writer never waits , it overwrites the oldest bytes and a reader that fell a full ring behind sees an overrun
+// one process creates it with EventRing , any number attach with RingReader
+#[repr(C)]
+pub struct RingHeader {
+    // bytes written since the ring was created , record n starts where head was before it
+    head: AtomicU64,
+    // end of the record being written , moves ahead of head before any byte is copied in
+    reserved: AtomicU64,
+    _pad: [u8; 48],
+    magic: AtomicU32,
+    capacity: AtomicU32,
+}
+
+const RING_MAGIC: u32 = 0xE7E1_0001;
+const HEADER_SIZE: usize = std::mem::size_of::<RingHeader>();
+const LEN_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingRead {
+    // the record starting at the asked position and where the next one starts
+    Record { next: u64 },
+    // nothing written there yet
+    Empty,
+    // the writer already went over that position , start again from `head`
+    Overrun { head: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub enum RingError {
+    FileOpen(String),
+    FileStat(String),
+    // the file is not the size its header says
+    InvalidSize { got: u64, expected: u64 },
+    Mmap(String),
+    // not a ring , or the writer has not finished creating it
+    InvalidMagic { got: u32 },
+    RecordTooLarge { len: usize, capacity: u64 },
+    Flush(String),
+}
+
+impl std::fmt::Display for RingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RingError::FileOpen(e) => write!(f, "Failed to open file: {}", e),
+            RingError::FileStat(e) => write!(f, "Failed to stat file: {}", e),
+            RingError::InvalidSize { got, expected } => {
+                write!(f, "Invalid ring file size: got {}, expected {}", got, expected)
+            }
+            RingError::Mmap(e) => write!(f, "Failed to mmap: {}", e),
+            RingError::InvalidMagic { got } => write!(f, "Invalid ring magic: got 0x{:X}", got),
+            RingError::RecordTooLarge { len, capacity } => {
+                write!(f, "Record of {} bytes does not fit a ring of {} bytes", len, capacity)
+            }
+            RingError::Flush(e) => write!(f, "Failed to flush: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RingError {}
+
+#[inline(always)]
+fn header(mapping: &[u8]) -> &RingHeader {
+    unsafe { &*(mapping.as_ptr() as *const RingHeader) }
+}
+
+fn read_at(mapping: &[u8], capacity: u64, pos: u64, out: &mut [u8]) {
+    let start = (pos % capacity) as usize;
+    let first = out.len().min(capacity as usize - start);
+    let data = &mapping[HEADER_SIZE..];
+    let (head, tail) = out.split_at_mut(first);
+    head.copy_from_slice(&data[start..start + first]);
+    tail.copy_from_slice(&data[..tail.len()]);
+}
+
+// reads the record starting at `pos` into `out` , the copy is checked against what the writer reserved
+// afterwards so a record torn by the writer comes back as an overrun
+fn read(mapping: &[u8], capacity: u64, pos: u64, out: &mut Vec<u8>) -> RingRead {
+    let header = header(mapping);
+    let head = header.head.load(Ordering::Acquire);
+    if pos >= head {
+        return RingRead::Empty;
+    }
+    if head - pos > capacity {
+        return RingRead::Overrun { head };
+    }
+    let mut len = [0u8; LEN_SIZE];
+    read_at(mapping, capacity, pos, &mut len);
+    let len = u32::from_le_bytes(len) as usize;
+    // a length torn by the writer can be anything
+    if (LEN_SIZE + len) as u64 > head - pos {
+        fence(Ordering::Acquire);
+        return if header.reserved.load(Ordering::Relaxed) - pos > capacity {
+            RingRead::Overrun { head: header.head.load(Ordering::Acquire) }
+        } else {
+            // `pos` is not where a record starts
+            RingRead::Empty
+        };
+    }
+    out.resize(len, 0);
+    read_at(mapping, capacity, pos + LEN_SIZE as u64, out);
+    fence(Ordering::Acquire);
+    if header.reserved.load(Ordering::Relaxed) - pos > capacity {
+        return RingRead::Overrun { head: header.head.load(Ordering::Acquire) };
+    }
+    RingRead::Record { next: pos + (LEN_SIZE + len) as u64 }
+}
+
+// the writer's handle
+#[derive(Debug)]
+pub struct EventRing {
+    mmap: MmapMut,
+    capacity: u64,
+}
+
+impl EventRing {
+    // creates or truncates the ring file , `capacity` is the size of the data area in bytes
+    pub fn create<P: AsRef<Path>>(path: P, capacity: u32) -> Result<Self, RingError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| RingError::FileOpen(e.to_string()))?;
+        file.set_len((HEADER_SIZE + capacity as usize) as u64)
+            .map_err(|e| RingError::FileStat(e.to_string()))?;
+        let mmap = unsafe { MmapMut::map_mut(&file) }.map_err(|e| RingError::Mmap(e.to_string()))?;
+        let ring = Self { mmap, capacity: capacity as u64 };
+        ring.header().capacity.store(capacity, Ordering::Relaxed);
+        ring.header().magic.store(RING_MAGIC, Ordering::Release);
+        Ok(ring)
+    }
+
+    #[inline(always)]
+    fn header(&self) -> &RingHeader {
+        header(&self.mmap)
+    }
+
+    pub fn head(&self) -> u64 {
+        self.header().head.load(Ordering::Acquire)
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    // copies `bytes` in at byte position `pos` , wrapping round the end of the data area
+    fn write_at(&mut self, pos: u64, bytes: &[u8]) {
+        let start = (pos % self.capacity) as usize;
+        let first = bytes.len().min(self.capacity as usize - start);
+        let data = &mut self.mmap[HEADER_SIZE..];
+        data[start..start + first].copy_from_slice(&bytes[..first]);
+        data[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+    }
+
+    pub fn push(&mut self, record: &[u8]) -> Result<(), RingError> {
+        if (LEN_SIZE + record.len()) as u64 > self.capacity {
+            return Err(RingError::RecordTooLarge { len: record.len(), capacity: self.capacity });
+        }
+        let head = self.header().head.load(Ordering::Relaxed);
+        let end = head + (LEN_SIZE + record.len()) as u64;
+        self.header().reserved.store(end, Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.write_at(head, &(record.len() as u32).to_le_bytes());
+        self.write_at(head + LEN_SIZE as u64, record);
+        self.header().head.store(end, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn read(&self, pos: u64, out: &mut Vec<u8>) -> RingRead {
+        read(&self.mmap, self.capacity, pos, out)
+    }
+
+    pub fn flush(&self) -> Result<(), RingError> {
+        self.mmap.flush().map_err(|e| RingError::Flush(e.to_string()))
+    }
+}
+
+// Only the publishing thread writes to it
+unsafe impl Send for EventRing {}
+
+// a reader attached to a ring another process created , it maps the file read only
+#[derive(Debug)]
+pub struct RingReader {
+    mmap: Mmap,
+    capacity: u64,
+}
+
+impl RingReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, RingError> {
+        let file = File::open(path).map_err(|e| RingError::FileOpen(e.to_string()))?;
+        let len = file.metadata().map_err(|e| RingError::FileStat(e.to_string()))?.len();
+        if len < HEADER_SIZE as u64 {
+            return Err(RingError::InvalidSize { got: len, expected: HEADER_SIZE as u64 });
+        }
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|e| RingError::Mmap(e.to_string()))?;
+        let magic = header(&mmap).magic.load(Ordering::Acquire);
+        if magic != RING_MAGIC {
+            return Err(RingError::InvalidMagic { got: magic });
+        }
+        let capacity = header(&mmap).capacity.load(Ordering::Relaxed) as u64;
+        if len != HEADER_SIZE as u64 + capacity || capacity == 0 {
+            return Err(RingError::InvalidSize { got: len, expected: HEADER_SIZE as u64 + capacity });
+        }
+        Ok(Self { mmap, capacity })
+    }
+
+    // where the next record will start , a reader joining late starts here
+    pub fn head(&self) -> u64 {
+        header(&self.mmap).head.load(Ordering::Acquire)
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn read(&self, pos: u64, out: &mut Vec<u8>) -> RingRead {
+        read(&self.mmap, self.capacity, pos, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_wraps_and_detects_overrun() {
+        let path = std::env::temp_dir().join(format!("test_event_ring_{}", std::process::id()));
+        let mut ring = EventRing::create(&path, 32).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(ring.read(0, &mut out), RingRead::Empty);
+
+        ring.push(b"0123456789").unwrap();
+        ring.push(b"abcdefghij").unwrap();
+        assert_eq!(ring.read(0, &mut out), RingRead::Record { next: 14 });
+        assert_eq!(out, b"0123456789");
+        // the third record wraps round the end of the data area
+        ring.push(b"KLMNOPQRST").unwrap();
+        assert_eq!(ring.read(28, &mut out), RingRead::Record { next: 42 });
+        assert_eq!(out, b"KLMNOPQRST");
+        assert_eq!(ring.read(0, &mut out), RingRead::Overrun { head: 42 });
+        assert!(ring.push(&[0; 29]).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_reader_attaches_to_a_ring() {
+        let path = std::env::temp_dir().join(format!("test_event_ring_reader_{}", std::process::id()));
+        let mut ring = EventRing::create(&path, 32).unwrap();
+        ring.push(b"0123456789").unwrap();
+        // a handle of its own , the way another process would map it
+        let reader = RingReader::open(&path).unwrap();
+        assert_eq!((reader.capacity(), reader.head()), (32, 14));
+        let mut out = Vec::new();
+        assert_eq!(reader.read(0, &mut out), RingRead::Record { next: 14 });
+        assert_eq!(out, b"0123456789");
+        assert_eq!(reader.read(14, &mut out), RingRead::Empty);
+        ring.push(b"abcdefghij").unwrap();
+        ring.push(b"KLMNOPQRST").unwrap();
+        assert_eq!(reader.read(14, &mut out), RingRead::Record { next: 28 });
+        assert_eq!(out, b"abcdefghij");
+        assert_eq!(reader.read(0, &mut out), RingRead::Overrun { head: 42 });
+
+        // anything that is not a whole ring is refused
+        std::fs::write(&path, [0u8; 100]).unwrap();
+        assert!(matches!(RingReader::open(&path), Err(RingError::InvalidMagic { got: 0 })));
+        std::fs::write(&path, [0u8; 10]).unwrap();
+        assert!(matches!(RingReader::open(&path), Err(RingError::InvalidSize { .. })));
+        drop(ring);
+        let _ = std::fs::remove_file(path);
+    }
+}
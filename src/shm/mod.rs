@@ -1 +1,2 @@
-pub mod queue;
\ No newline at end of file
+pub mod queue;
+pub mod event_ring;
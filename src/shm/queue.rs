@@ -36,15 +36,22 @@ pub struct QueueHeader {
     capacity: AtomicU32,      // offset 132
 }
 
-const QUEUE_MAGIC: u32 = 0xDEADBEEF;
+// Wire version of the queue file, the producer writes it and the engine refuses anything else.
+// 0xDEADBEEF was the 48 byte ShmOrder. 0xDEADBE64 is the 64 byte one: the 48 byte layout is unchanged
+// up to byte 43 (order_type .. session_state sit in the old padding) and stop_price (offset 48),
+// display_qty (offset 56) plus 4 reserved bytes are appended. Producers must write the new magic
+// and size the file for 64 byte slots.
+const QUEUE_MAGIC: u32 = 0xDEADBE64;
 const QUEUE_CAPACITY: usize = 65536;
 const ORDER_SIZE: usize = std::mem::size_of::<ShmOrder>();
 const HEADER_SIZE: usize = std::mem::size_of::<QueueHeader>();
 const TOTAL_SIZE: usize = HEADER_SIZE + (QUEUE_CAPACITY * ORDER_SIZE);
 
 // Compile-time layout assertions (fail build if wrong)
-const _: () = assert!(ORDER_SIZE == 48, "Order must be 48 bytes");
+const _: () = assert!(ORDER_SIZE == 64, "Order must be 64 bytes");
 const _: () = assert!(HEADER_SIZE == 136, "QueueHeader must be 136 bytes");
+const _: () = assert!(std::mem::offset_of!(ShmOrder, stop_price) == 48, "stop_price must follow the 48 byte layout");
+const _: () = assert!(std::mem::offset_of!(ShmOrder, display_qty) == 56, "display_qty must be at offset 56");
 const _: () = {
     // Verify ConsumerTail is at offset 64
     assert!(
@@ -111,12 +118,6 @@ impl Queue {
         })
     }
 
-    /// Get mutable header reference - ZERO COST
-    #[inline(always)]
-    fn header_mut(&self) -> &mut QueueHeader {
-        unsafe { &mut *self.header_ptr }
-    }
-
     /// Get immutable header reference - ZERO COST
     #[inline(always)]
     fn header(&self) -> &QueueHeader {
@@ -140,7 +141,7 @@ impl Queue {
     /// ULTRA-FAST dequeue - all pointers cached, no borrows
     #[inline]
     pub fn dequeue(&mut self) -> Result<Option<ShmOrder>, QueueError> {
-        let header = self.header_mut();
+        let header = self.header();
 
         let producer_head = header.producer_head.load(Ordering::Acquire);
         let consumer_tail = header.consumer_tail.load(Ordering::Relaxed);
@@ -160,7 +161,7 @@ impl Queue {
     }
 
     pub fn enqueue(&mut self, order: ShmOrder) -> Result<(), QueueError> {
-        let header = self.header_mut();
+        let header = self.header();
 
         let consumer_tail = header.consumer_tail.load(Ordering::Acquire);
         let producer_head = header.producer_head.load(Ordering::Relaxed);
@@ -181,6 +182,11 @@ impl Queue {
         Ok(())
     }
 
+    /// Records taken off the queue so far , the position of the next one to dequeue
+    pub fn consumed(&self) -> u64 {
+        self.header().consumer_tail.load(Ordering::Relaxed)
+    }
+
     pub fn depth(&self) -> u64 {
         let header = self.header();
         let producer_head = header.producer_head.load(Ordering::Relaxed);
@@ -269,7 +275,7 @@ mod tests {
 
     #[test]
     fn test_layout() {
-        assert_eq!(ORDER_SIZE, 40, "Order must be 40 bytes");
+        assert_eq!(ORDER_SIZE, 64, "Order must be 64 bytes");
         assert_eq!(HEADER_SIZE, 136, "QueueHeader must be 136 bytes");
         assert_eq!(
             std::mem::offset_of!(QueueHeader, consumer_tail),